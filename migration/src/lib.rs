@@ -3,6 +3,24 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table;
 mod m20250627_071849_push_subscriptions;
 mod m20260403_104503_add_last_synced_at_to_feeds;
+mod m20261016_090000_add_http_cache_headers_to_feeds;
+mod m20261016_091500_add_sync_schedule_to_feeds;
+mod m20261016_093000_add_guid_to_posts;
+mod m20261016_094500_post_revisions;
+mod m20261016_100000_add_health_to_feeds;
+mod m20261016_101500_add_fetch_full_content_to_feeds;
+mod m20261016_103000_enclosures;
+mod m20261016_104500_playback_progress;
+mod m20261016_110000_add_author_to_posts;
+mod m20261016_111500_add_first_seen_at_to_posts;
+mod m20261016_113000_feed_redirects;
+mod m20261016_114500_retention;
+mod m20261016_120000_add_request_settings_to_feeds;
+mod m20261016_121500_add_proxy_to_feeds;
+mod m20261016_123000_add_accept_invalid_certs_to_feeds;
+mod m20261017_090000_add_sync_hints_to_feeds;
+mod m20261017_091500_add_site_images_checked_at_to_feeds;
+mod m20261017_093000_normalize_post_publish_times;
 
 pub struct Migrator;
 
@@ -13,6 +31,24 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250627_071849_push_subscriptions::Migration),
             Box::new(m20260403_104503_add_last_synced_at_to_feeds::Migration),
+            Box::new(m20261016_090000_add_http_cache_headers_to_feeds::Migration),
+            Box::new(m20261016_091500_add_sync_schedule_to_feeds::Migration),
+            Box::new(m20261016_093000_add_guid_to_posts::Migration),
+            Box::new(m20261016_094500_post_revisions::Migration),
+            Box::new(m20261016_100000_add_health_to_feeds::Migration),
+            Box::new(m20261016_101500_add_fetch_full_content_to_feeds::Migration),
+            Box::new(m20261016_103000_enclosures::Migration),
+            Box::new(m20261016_104500_playback_progress::Migration),
+            Box::new(m20261016_110000_add_author_to_posts::Migration),
+            Box::new(m20261016_111500_add_first_seen_at_to_posts::Migration),
+            Box::new(m20261016_113000_feed_redirects::Migration),
+            Box::new(m20261016_114500_retention::Migration),
+            Box::new(m20261016_120000_add_request_settings_to_feeds::Migration),
+            Box::new(m20261016_121500_add_proxy_to_feeds::Migration),
+            Box::new(m20261016_123000_add_accept_invalid_certs_to_feeds::Migration),
+            Box::new(m20261017_090000_add_sync_hints_to_feeds::Migration),
+            Box::new(m20261017_091500_add_site_images_checked_at_to_feeds::Migration),
+            Box::new(m20261017_093000_normalize_post_publish_times::Migration),
         ]
     }
 }
@@ -0,0 +1,105 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't drop the unique constraint on `url` in place, so the
+        // table is rebuilt. Existing posts use their url as the guid, and pick
+        // up their real guid the next time their feed is synced.
+        manager
+            .create_table(
+                Table::create()
+                    .table("posts_new")
+                    .col(pk_uuid("id"))
+                    .col(uuid("feed_id"))
+                    .col(string("guid"))
+                    .col(string_null("url"))
+                    .col(string("title"))
+                    .col(string("publish_time"))
+                    .col(string_null("description"))
+                    .col(string_null("content"))
+                    .col(string_null("thumbnail"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("feed_id")
+                            .to_tbl("feeds")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO posts_new (id, feed_id, guid, url, title, publish_time, description, content, thumbnail)
+                 SELECT id, feed_id, url, url, title, publish_time, description, content, thumbnail FROM posts",
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table("posts").to_owned())
+            .await?;
+
+        manager
+            .rename_table(Table::rename().table("posts_new", "posts").to_owned())
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_feed_id_guid")
+                    .table("posts")
+                    .col("feed_id")
+                    .col("guid")
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table("posts_old")
+                    .col(pk_uuid("id"))
+                    .col(uuid("feed_id"))
+                    .col(string_uniq("url"))
+                    .col(string("title"))
+                    .col(string("publish_time"))
+                    .col(string_null("description"))
+                    .col(string_null("content"))
+                    .col(string_null("thumbnail"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("feed_id")
+                            .to_tbl("feeds")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Posts without a url, or sharing a url with another post, can't be
+        // represented in the old schema and are dropped.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT OR IGNORE INTO posts_old (id, feed_id, url, title, publish_time, description, content, thumbnail)
+                 SELECT id, feed_id, url, title, publish_time, description, content, thumbnail FROM posts WHERE url IS NOT NULL",
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table("posts").to_owned())
+            .await?;
+
+        manager
+            .rename_table(Table::rename().table("posts_old", "posts").to_owned())
+            .await
+    }
+}
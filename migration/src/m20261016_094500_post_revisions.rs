@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("posts")
+                    .add_column(string_null("updated_time"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table("post_revisions")
+                    .col(pk_auto("id"))
+                    .col(uuid("post_id"))
+                    .col(string("title"))
+                    .col(string_null("description"))
+                    .col(string_null("content"))
+                    .col(string_null("thumbnail"))
+                    .col(string_null("updated_time"))
+                    .col(big_integer("revised_at"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("post_id")
+                            .to_tbl("posts")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table("post_revisions").to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("posts")
+                    .drop_column(Alias::new("updated_time"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
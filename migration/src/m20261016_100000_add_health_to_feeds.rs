@@ -0,0 +1,52 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COLUMNS: &[&str] = &[
+    "last_error",
+    "last_error_at",
+    "consecutive_failures",
+    "last_success_at",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            string_null("last_error"),
+            big_integer_null("last_error_at"),
+            integer("consecutive_failures").default(0).to_owned(),
+            big_integer_null("last_success_at"),
+        ];
+
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table("feeds").add_column(column).to_owned())
+                .await?;
+        }
+
+        // Feeds that have synced before were healthy as far as we know.
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE feeds SET last_success_at = last_synced_at")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS.iter().rev() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table("feeds")
+                        .drop_column(Alias::new(*column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table("enclosures")
+                    .col(pk_auto("id"))
+                    .col(uuid("post_id"))
+                    .col(string("url"))
+                    .col(string_null("mime_type"))
+                    .col(big_integer_null("length"))
+                    .col(big_integer_null("duration"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("post_id")
+                            .to_tbl("posts")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .add_column(boolean("is_podcast").default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .drop_column(Alias::new("is_podcast"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table("enclosures").to_owned())
+            .await
+    }
+}
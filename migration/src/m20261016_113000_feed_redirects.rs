@@ -0,0 +1,51 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table("previous_feed_urls")
+                    .col(pk_auto("id"))
+                    .col(uuid("feed_id"))
+                    .col(string("url").unique_key())
+                    .col(big_integer("moved_at"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("feed_id")
+                            .to_tbl("feeds")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .add_column(boolean("active").default(true))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .drop_column(Alias::new("active"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table("previous_feed_urls").to_owned())
+            .await
+    }
+}
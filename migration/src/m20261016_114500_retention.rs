@@ -0,0 +1,111 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("posts")
+                    .add_column(boolean("starred").default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .add_column(big_integer_null("retention_max_age_days"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .add_column(big_integer_null("retention_keep_last"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table("post_tombstones")
+                    .col(pk_auto("id"))
+                    .col(uuid("feed_id"))
+                    .col(string("guid"))
+                    .col(string_null("url"))
+                    .col(big_integer("pruned_at"))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from_col("feed_id")
+                            .to_tbl("feeds")
+                            .to_col("id"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_tombstones_feed_id_guid")
+                    .table("post_tombstones")
+                    .col("feed_id")
+                    .col("guid")
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_tombstones_feed_id_url")
+                    .table("post_tombstones")
+                    .col("feed_id")
+                    .col("url")
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table("post_tombstones").to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .drop_column(Alias::new("retention_keep_last"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("feeds")
+                    .drop_column(Alias::new("retention_max_age_days"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table("posts")
+                    .drop_column(Alias::new("starred"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
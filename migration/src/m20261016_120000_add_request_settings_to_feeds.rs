@@ -0,0 +1,50 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COLUMNS: &[&str] = &[
+    "auth_username",
+    "auth_password",
+    "auth_token",
+    "user_agent",
+    "cookie",
+    "headers",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            string_null("auth_username"),
+            string_null("auth_password"),
+            string_null("auth_token"),
+            string_null("user_agent"),
+            string_null("cookie"),
+            json_null("headers"),
+        ];
+
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table("feeds").add_column(column).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS.iter().rev() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table("feeds")
+                        .drop_column(Alias::new(*column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
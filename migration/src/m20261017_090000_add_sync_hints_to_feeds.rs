@@ -0,0 +1,40 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COLUMNS: &[&str] = &["min_sync_interval", "skip_hours", "skip_days"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            big_integer_null("min_sync_interval"),
+            json_null("skip_hours"),
+            json_null("skip_days"),
+        ];
+
+        for column in columns {
+            manager
+                .alter_table(Table::alter().table("feeds").add_column(column).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS.iter().rev() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table("feeds")
+                        .drop_column(Alias::new(*column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Posts are ordered by the text of their publish time, which only
+        // matches chronological order if every time is in the same offset.
+        // SQLite's date functions convert times with an offset to UTC, and
+        // return null for anything they can't parse, which is left alone.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE posts
+                 SET publish_time = strftime('%Y-%m-%dT%H:%M:%S', publish_time) || '+00:00'
+                 WHERE publish_time NOT LIKE '%+00:00'
+                   AND strftime('%Y-%m-%dT%H:%M:%S', publish_time) IS NOT NULL",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The original offsets are gone, and UTC times are still valid.
+        Ok(())
+    }
+}
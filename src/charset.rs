@@ -0,0 +1,231 @@
+//! Character encoding detection and transcoding for fetched documents.
+//!
+//! Encodings are taken from, in order of preference, a byte order mark, the
+//! `Content-Type` header, and the document itself (the XML declaration for
+//! feeds, `<meta>` tags for HTML pages). Declared encodings that the content
+//! isn't actually valid in are skipped, since servers often send a default
+//! charset regardless of the document.
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// How far into a document to look for an in-document encoding declaration.
+const PRESCAN_LENGTH: usize = 1024;
+
+/// Decodes a feed document to UTF-8. The XML declaration is rewritten to
+/// match, so that parsers don't try to decode it again.
+pub fn decode_feed(content: &[u8], content_type: Option<&str>) -> String {
+    let declared = xml_declared_encoding(content);
+    let decoded = decode(content, content_type, declared);
+    rewrite_xml_declaration(decoded)
+}
+
+/// Decodes an HTML page to UTF-8.
+pub fn decode_html(content: &[u8], content_type: Option<&str>) -> String {
+    let declared = html_declared_encoding(content);
+    decode(content, content_type, declared)
+}
+
+fn decode(
+    content: &[u8],
+    content_type: Option<&str>,
+    declared: Option<&'static Encoding>,
+) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(content) {
+        let (text, _) = encoding.decode_without_bom_handling(&content[bom_length..]);
+        return text.into_owned();
+    }
+
+    let candidates = [
+        content_type.and_then(content_type_charset),
+        declared,
+        Some(UTF_8),
+    ];
+
+    for encoding in candidates.into_iter().flatten() {
+        if let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(content) {
+            return text.into_owned();
+        }
+    }
+
+    // Anything that isn't valid UTF-8 is most likely a legacy Western
+    // encoding, which can decode any byte sequence.
+    let (text, _) = WINDOWS_1252.decode_without_bom_handling(content);
+    text.into_owned()
+}
+
+fn content_type_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| lookup(value))
+}
+
+/// Reads the `encoding` of an `<?xml ...?>` declaration.
+fn xml_declared_encoding(content: &[u8]) -> Option<&'static Encoding> {
+    let start = &content[..content.len().min(PRESCAN_LENGTH)];
+    let start = String::from_utf8_lossy(start);
+    let declaration = start.trim_start().strip_prefix("<?xml")?;
+    let declaration = &declaration[..declaration.find("?>")?];
+
+    attribute_value(declaration, "encoding").and_then(lookup)
+}
+
+/// Finds a charset declared by a `<meta charset>` or
+/// `<meta http-equiv="Content-Type">` tag.
+fn html_declared_encoding(content: &[u8]) -> Option<&'static Encoding> {
+    let start = &content[..content.len().min(PRESCAN_LENGTH)];
+    let start = String::from_utf8_lossy(start).to_ascii_lowercase();
+
+    start.match_indices("<meta").find_map(|(index, _)| {
+        let tag = &start[index..];
+        let tag = &tag[..tag.find('>')?];
+        let value = &tag[tag.find("charset")? + "charset".len()..];
+        let value = value.trim_start().strip_prefix('=')?;
+        let value = value.trim_start().trim_start_matches(['"', '\'']);
+        let end = value
+            .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+            .unwrap_or(value.len());
+
+        lookup(&value[..end])
+    })
+}
+
+fn lookup(label: &str) -> Option<&'static Encoding> {
+    let label = label.trim().trim_matches(['"', '\'']);
+    let encoding = Encoding::for_label(label.as_bytes())?;
+
+    // A document that declares itself as UTF-16 without a BOM can't actually
+    // be UTF-16, since the declaration itself was readable as ASCII.
+    if encoding.output_encoding() != encoding {
+        return Some(encoding.output_encoding());
+    }
+
+    Some(encoding)
+}
+
+fn attribute_value<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let value = &tag[tag.find(name)? + name.len()..];
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(&value[..value.find(quote)?])
+}
+
+/// Replaces the encoding in the XML declaration of a decoded document.
+fn rewrite_xml_declaration(text: String) -> String {
+    let Some(end) = text
+        .trim_start()
+        .starts_with("<?xml")
+        .then(|| text.find("?>"))
+        .flatten()
+    else {
+        return text;
+    };
+
+    let Some(value) = attribute_value(&text[..end], "encoding") else {
+        return text;
+    };
+
+    if value.eq_ignore_ascii_case("utf-8") {
+        return text;
+    }
+
+    // The value is a slice of the declaration, so its position is known.
+    let start = value.as_ptr() as usize - text.as_ptr() as usize;
+    let range = start..start + value.len();
+
+    let mut text = text;
+    text.replace_range(range, "UTF-8");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TITLE: &str = "Café “naïve” – €5";
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/tests/fixtures/encoding/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+    }
+
+    fn rss_title(content: &str) -> String {
+        rss::Channel::read_from(content.as_bytes()).unwrap().title
+    }
+
+    #[test]
+    fn decodes_feed_with_xml_declaration() {
+        let content = decode_feed(&fixture("windows-1252-declaration.xml"), None);
+        assert_eq!(rss_title(&content), TITLE);
+        assert!(content.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    }
+
+    #[test]
+    fn decodes_latin1_feed_with_xml_declaration() {
+        let content = decode_feed(&fixture("iso-8859-1-declaration.xml"), None);
+        assert_eq!(rss_title(&content), "Café naïve à Zürich");
+    }
+
+    #[test]
+    fn decodes_feed_with_content_type() {
+        let content = decode_feed(
+            &fixture("windows-1252-undeclared.xml"),
+            Some("application/rss+xml; charset=windows-1252"),
+        );
+        assert_eq!(rss_title(&content), TITLE);
+    }
+
+    #[test]
+    fn ignores_wrong_utf8_content_type() {
+        let content = decode_feed(
+            &fixture("windows-1252-declaration.xml"),
+            Some("text/xml; charset=utf-8"),
+        );
+        assert_eq!(rss_title(&content), TITLE);
+    }
+
+    #[test]
+    fn decodes_feed_with_utf8_bom() {
+        let content = decode_feed(&fixture("utf-8-bom.xml"), None);
+        assert_eq!(rss_title(&content), TITLE);
+    }
+
+    #[test]
+    fn decodes_feed_with_utf16_bom() {
+        let content = decode_feed(&fixture("utf-16le-bom.xml"), None);
+        assert_eq!(rss_title(&content), TITLE);
+    }
+
+    #[test]
+    fn falls_back_to_windows_1252() {
+        let content = decode_feed(&fixture("windows-1252-undeclared.xml"), None);
+        assert_eq!(rss_title(&content), TITLE);
+    }
+
+    #[test]
+    fn decodes_html_with_meta_charset() {
+        let content = decode_html(&fixture("meta-charset.html"), Some("text/html"));
+        assert!(content.contains(TITLE));
+    }
+
+    #[test]
+    fn decodes_html_with_meta_http_equiv() {
+        let content = decode_html(&fixture("meta-http-equiv.html"), None);
+        assert!(content.contains("Café naïve à Zürich"));
+    }
+
+    #[test]
+    fn decodes_html_with_content_type() {
+        let content = decode_html(
+            &fixture("shift-jis-undeclared.html"),
+            Some("text/html; charset=Shift_JIS"),
+        );
+        assert!(content.contains("日本語のページ"));
+    }
+}
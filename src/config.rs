@@ -1,6 +1,15 @@
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::Certificate;
+
 pub struct Config {
     pub database_url: String,
     pub oidc: Option<OidcConfig>,
+    pub sync: SyncConfig,
+    pub retention: RetentionConfig,
+    pub fetch: FetchConfig,
 }
 
 impl Config {
@@ -9,10 +18,141 @@ impl Config {
             database_url: std::env::var("DATABASE_URL")
                 .unwrap_or_else(|_| "sqlite://data/tress.db?mode=rwc".to_owned()),
             oidc: OidcConfig::from_env()?,
+            sync: SyncConfig::from_env()?,
+            retention: RetentionConfig::from_env()?,
+            fetch: FetchConfig::from_env()?,
+        })
+    }
+}
+
+pub struct SyncConfig {
+    /// Maximum number of feeds synced at the same time.
+    pub concurrency: usize,
+    /// Maximum number of concurrent requests made to a single host.
+    pub per_host_concurrency: usize,
+}
+
+impl SyncConfig {
+    pub fn from_env() -> eyre::Result<Self> {
+        Ok(SyncConfig {
+            // Either being 0 would stop syncing altogether.
+            concurrency: parse_env::<NonZeroUsize>("SYNC_CONCURRENCY")?
+                .map_or(8, NonZeroUsize::get),
+            per_host_concurrency: parse_env::<NonZeroUsize>("SYNC_PER_HOST_CONCURRENCY")?
+                .map_or(2, NonZeroUsize::get),
+        })
+    }
+}
+
+/// Default retention policy, which feeds can override. Starred posts are
+/// always kept.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RetentionConfig {
+    /// Posts published more than this many days ago are pruned.
+    pub max_age_days: Option<i64>,
+    /// Only this many of the most recent posts of each feed are kept.
+    pub keep_last: Option<i64>,
+}
+
+impl RetentionConfig {
+    pub fn from_env() -> eyre::Result<Self> {
+        Ok(RetentionConfig {
+            max_age_days: parse_env("RETENTION_MAX_AGE_DAYS")?,
+            keep_last: parse_env("RETENTION_KEEP_LAST")?,
+        })
+    }
+}
+
+/// Limits on outgoing requests made on behalf of users, e.g. for feeds and
+/// the pages they link to.
+#[derive(Clone, Debug)]
+pub struct FetchConfig {
+    pub connect_timeout: Duration,
+    /// Maximum time to wait for more data while reading a response.
+    pub read_timeout: Duration,
+    /// Maximum time for a whole request, including reading the body.
+    pub timeout: Duration,
+    /// Maximum size of a response body after decompression, in bytes.
+    pub max_body_size: usize,
+    /// Whether feeds and pages may be fetched from private, loopback and
+    /// link-local addresses.
+    pub allow_private_addresses: bool,
+    /// Hosts that may be fetched from even if they have a private address.
+    /// Subdomains are included.
+    pub allowed_hosts: Vec<String>,
+    /// Hosts that are never fetched from. Subdomains are included.
+    pub denied_hosts: Vec<String>,
+    /// Proxy for all outgoing requests, e.g. `http://proxy:3128` or
+    /// `socks5h://localhost:9050`. Feeds can override it.
+    pub proxy: Option<String>,
+    /// Proxy hosts that feeds may use even if they have a private address,
+    /// e.g. a local Tor daemon. Subdomains are included.
+    pub allowed_proxies: Vec<String>,
+    /// Extra CA certificates to trust, on top of the built-in ones.
+    pub ca_certificates: Vec<Certificate>,
+}
+
+impl FetchConfig {
+    pub fn from_env() -> eyre::Result<Self> {
+        Ok(FetchConfig {
+            connect_timeout: Duration::from_secs(
+                parse_env("FETCH_CONNECT_TIMEOUT_SECS")?.unwrap_or(10),
+            ),
+            read_timeout: Duration::from_secs(parse_env("FETCH_READ_TIMEOUT_SECS")?.unwrap_or(30)),
+            timeout: Duration::from_secs(parse_env("FETCH_TIMEOUT_SECS")?.unwrap_or(120)),
+            max_body_size: parse_env("FETCH_MAX_BODY_SIZE")?.unwrap_or(10 * 1024 * 1024),
+            allow_private_addresses: parse_env("FETCH_ALLOW_PRIVATE_ADDRESSES")?.unwrap_or(false),
+            allowed_hosts: parse_list_env("FETCH_ALLOWED_HOSTS"),
+            denied_hosts: parse_list_env("FETCH_DENIED_HOSTS"),
+            proxy: std::env::var("FETCH_PROXY").ok(),
+            allowed_proxies: parse_list_env("FETCH_ALLOWED_PROXIES"),
+            ca_certificates: load_certificates(&parse_list_env("FETCH_CA_CERTIFICATES"))?,
         })
     }
 }
 
+/// Reads PEM bundles, each of which can hold several certificates.
+fn load_certificates(paths: &[String]) -> eyre::Result<Vec<Certificate>> {
+    let mut certificates = Vec::new();
+
+    for path in paths {
+        let pem = std::fs::read(path)
+            .map_err(|e| eyre::eyre!("failed to read CA certificates from {path}: {e}"))?;
+        certificates.extend(
+            Certificate::from_pem_bundle(&pem)
+                .map_err(|e| eyre::eyre!("invalid CA certificates in {path}: {e}"))?,
+        );
+    }
+
+    Ok(certificates)
+}
+
+fn parse_list_env(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_owned())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_env<T>(name: &str) -> eyre::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| eyre::eyre!("invalid value for {name}: {e}")),
+        Err(_) => Ok(None),
+    }
+}
+
 #[derive(Clone)]
 pub struct OidcConfig {
     pub issuer_url: String,
@@ -0,0 +1,475 @@
+//! Lenient parsing of the dates found in feeds.
+//!
+//! RSS requires RFC 2822 dates, but feeds in the wild use ISO 8601, drop the
+//! weekday, use named timezones or localized month names. Dates are normalized
+//! into a canonical form and then tried against a list of known formats.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+
+/// Month names and abbreviations in English, German, French, Spanish, Italian,
+/// Dutch and Portuguese.
+const MONTHS: [&[&str]; 12] = [
+    &[
+        "jan", "january", "januar", "janv", "janvier", "ene", "enero", "gen", "gennaio", "januari",
+        "janeiro",
+    ],
+    &[
+        "feb",
+        "february",
+        "februar",
+        "fév",
+        "févr",
+        "fev",
+        "fevr",
+        "février",
+        "fevrier",
+        "febrero",
+        "febbraio",
+        "februari",
+        "fevereiro",
+    ],
+    &[
+        "mar", "march", "mär", "mrz", "märz", "maerz", "mars", "marzo", "mrt", "maart", "março",
+        "marco",
+    ],
+    &["apr", "april", "avr", "avril", "abr", "abril", "aprile"],
+    &["may", "mai", "mayo", "mag", "maggio", "mei", "maio"],
+    &[
+        "jun", "june", "juni", "juin", "junio", "giu", "giugno", "junho",
+    ],
+    &[
+        "jul", "july", "juli", "juil", "juillet", "julio", "lug", "luglio", "julho",
+    ],
+    &[
+        "aug", "august", "aoû", "août", "aout", "ago", "agosto", "augustus",
+    ],
+    &[
+        "sep",
+        "sept",
+        "september",
+        "septembre",
+        "septiembre",
+        "setiembre",
+        "set",
+        "settembre",
+        "setembro",
+    ],
+    &[
+        "oct", "october", "okt", "oktober", "octobre", "octubre", "ott", "ottobre", "out",
+        "outubro",
+    ],
+    &["nov", "november", "novembre", "noviembre", "novembro"],
+    &[
+        "dec",
+        "december",
+        "dez",
+        "dezember",
+        "déc",
+        "décembre",
+        "decembre",
+        "dic",
+        "diciembre",
+        "dicembre",
+        "dezembro",
+    ],
+];
+
+const MONTH_ABBREVIATIONS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Commonly used timezone abbreviations. Some of these are ambiguous, in
+/// which case the most common meaning is used.
+const TIMEZONES: &[(&str, &str)] = &[
+    ("UT", "+0000"),
+    ("UTC", "+0000"),
+    ("GMT", "+0000"),
+    ("Z", "+0000"),
+    ("WET", "+0000"),
+    ("WEST", "+0100"),
+    ("BST", "+0100"),
+    ("CET", "+0100"),
+    ("MEZ", "+0100"),
+    ("CEST", "+0200"),
+    ("MESZ", "+0200"),
+    ("EET", "+0200"),
+    ("EEST", "+0300"),
+    ("MSK", "+0300"),
+    ("IST", "+0530"),
+    ("SGT", "+0800"),
+    ("HKT", "+0800"),
+    ("AWST", "+0800"),
+    ("JST", "+0900"),
+    ("KST", "+0900"),
+    ("ACST", "+0930"),
+    ("AEST", "+1000"),
+    ("AEDT", "+1100"),
+    ("NZST", "+1200"),
+    ("NZDT", "+1300"),
+    ("HST", "-1000"),
+    ("AKST", "-0900"),
+    ("AKDT", "-0800"),
+    ("PST", "-0800"),
+    ("PDT", "-0700"),
+    ("MST", "-0700"),
+    ("MDT", "-0600"),
+    ("CST", "-0600"),
+    ("CDT", "-0500"),
+    ("EST", "-0500"),
+    ("EDT", "-0400"),
+    ("AST", "-0400"),
+    ("ADT", "-0300"),
+];
+
+const FORMATS_WITH_OFFSET: &[&str] = &[
+    "%d %b %Y %H:%M:%S%.f %#z",
+    "%d %b %Y %H:%M %#z",
+    "%d %b %y %H:%M:%S %#z",
+    "%d %b %y %H:%M %#z",
+    "%b %d %Y %H:%M:%S%.f %#z",
+    "%b %d %Y %H:%M %#z",
+    "%b %d %Y %I:%M:%S %p %#z",
+    "%b %d %Y %I:%M %p %#z",
+    "%d %b %Y %I:%M:%S %p %#z",
+    "%d %b %Y %I:%M %p %#z",
+    "%Y-%m-%dT%H:%M:%S%.f%#z",
+    "%Y-%m-%dT%H:%M%#z",
+    "%Y-%m-%dT%H:%M:%S%.f %#z",
+    "%Y-%m-%d %H:%M:%S%.f%#z",
+    "%Y-%m-%d %H:%M:%S%.f %#z",
+    "%Y-%m-%d %H:%M %#z",
+    "%Y/%m/%d %H:%M:%S %#z",
+];
+
+/// Formats without a timezone, which are assumed to be in UTC.
+const NAIVE_FORMATS: &[&str] = &[
+    "%d %b %Y %H:%M:%S%.f",
+    "%d %b %Y %H:%M",
+    "%b %d %Y %H:%M:%S%.f",
+    "%b %d %Y %H:%M",
+    "%b %d %Y %I:%M:%S %p",
+    "%b %d %Y %I:%M %p",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+];
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d %b %Y", "%b %d %Y", "%Y/%m/%d"];
+
+/// Parses a date in any of the formats commonly found in feeds.
+pub fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc2822(date) {
+        return Some(date);
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date);
+    }
+
+    let tokens = normalize(date);
+
+    // A leading word is usually a weekday, but some abbreviations like "mar"
+    // could also be the month of a US style date, so try both.
+    let starts_with_word = tokens.len() > 1 && tokens[0].chars().all(char::is_alphabetic);
+
+    let candidates = if starts_with_word {
+        vec![tokens[1..].join(" "), tokens.join(" ")]
+    } else {
+        vec![tokens.join(" ")]
+    };
+
+    candidates.iter().find_map(|date| parse_normalized(date))
+}
+
+/// Parses the publish date of an RSS item, falling back to its Dublin Core and
+/// Atom dates.
+pub fn rss_item_published(
+    item: &rss::Item,
+    namespaces: &BTreeMap<String, String>,
+) -> Option<DateTime<FixedOffset>> {
+    item.pub_date
+        .as_deref()
+        .and_then(parse_date)
+        .or_else(|| {
+            item.dublin_core_ext
+                .as_ref()
+                .and_then(|dc| dc.dates.iter().find_map(|date| parse_date(date)))
+        })
+        .or_else(|| rss_atom_date(item, namespaces, "published"))
+        .or_else(|| rss_atom_date(item, namespaces, "updated"))
+}
+
+/// Parses the `atom:updated` date of an RSS item.
+pub fn rss_item_updated(
+    item: &rss::Item,
+    namespaces: &BTreeMap<String, String>,
+) -> Option<DateTime<FixedOffset>> {
+    rss_atom_date(item, namespaces, "updated")
+}
+
+fn rss_atom_date(
+    item: &rss::Item,
+    namespaces: &BTreeMap<String, String>,
+    name: &str,
+) -> Option<DateTime<FixedOffset>> {
+    // Extensions are keyed by the prefix the feed declared, which isn't
+    // always `atom`.
+    let prefixes = namespaces
+        .iter()
+        .filter(|(_, ns)| ns.as_str() == ATOM_NS)
+        .map(|(prefix, _)| prefix.as_str())
+        .chain(["atom"]);
+
+    prefixes
+        .filter_map(|prefix| item.extensions.get(prefix))
+        .filter_map(|ext| ext.get(name))
+        .flatten()
+        .filter_map(|ext| ext.value.as_deref())
+        .find_map(parse_date)
+}
+
+/// Splits a date into tokens, dropping punctuation and comments and replacing
+/// localized month names and timezone abbreviations.
+fn normalize(date: &str) -> Vec<String> {
+    let date = date.replace(',', " ");
+
+    let mut tokens = vec![];
+
+    for token in date.split_whitespace() {
+        // Comments like "(UTC)" after an offset.
+        if token.starts_with('(') {
+            break;
+        }
+
+        let word = token.trim_end_matches('.').to_lowercase();
+
+        if let Some(month) = MONTHS
+            .iter()
+            .position(|names| names.contains(&word.as_str()))
+        {
+            tokens.push(MONTH_ABBREVIATIONS[month].to_owned());
+        } else if let Some(offset) = timezone_offset(token) {
+            tokens.push(offset);
+        } else {
+            tokens.push(token.to_owned());
+        }
+    }
+
+    tokens
+}
+
+/// Converts a timezone abbreviation like "EST" or "GMT+2" into an offset.
+fn timezone_offset(token: &str) -> Option<String> {
+    let upper = token.to_ascii_uppercase();
+
+    if let Some((_, offset)) = TIMEZONES.iter().find(|(name, _)| *name == upper) {
+        return Some((*offset).to_owned());
+    }
+
+    let offset = ["GMT", "UTC", "UT"]
+        .iter()
+        .find_map(|prefix| upper.strip_prefix(prefix))?;
+
+    let (sign, offset) = offset.split_at_checked(1)?;
+    if sign != "+" && sign != "-" {
+        return None;
+    }
+
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if offset.len() <= 2 => (offset, "0"),
+        None => offset.split_at_checked(offset.len() - 2)?,
+    };
+
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+
+    Some(format!("{sign}{hours:02}{minutes:02}"))
+}
+
+fn parse_normalized(date: &str) -> Option<DateTime<FixedOffset>> {
+    for format in FORMATS_WITH_OFFSET {
+        if let Ok(date) = DateTime::parse_from_str(date, format) {
+            return Some(date);
+        }
+    }
+
+    for format in NAIVE_FORMATS {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date, format) {
+            return Some(date.and_utc().fixed_offset());
+        }
+    }
+
+    for format in DATE_FORMATS {
+        if let Ok(date) = NaiveDate::parse_from_str(date, format) {
+            return Some(date.and_time(Default::default()).and_utc().fixed_offset());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_date_corpus() {
+        let corpus = [
+            // Valid RFC 2822
+            (
+                "Tue, 05 Mar 2024 10:00:00 +0000",
+                "2024-03-05T10:00:00+00:00",
+            ),
+            ("Tue, 05 Mar 2024 10:00:00 GMT", "2024-03-05T10:00:00+00:00"),
+            // Missing weekday
+            ("05 Mar 2024 10:00:00 +0100", "2024-03-05T10:00:00+01:00"),
+            ("5 Mar 2024 10:00 +0100", "2024-03-05T10:00:00+01:00"),
+            // Wrong weekday
+            (
+                "Mon, 05 Mar 2024 10:00:00 +0000",
+                "2024-03-05T10:00:00+00:00",
+            ),
+            // Full weekday and month names
+            (
+                "Tuesday, 05 March 2024 10:00:00 +0000",
+                "2024-03-05T10:00:00+00:00",
+            ),
+            // Named timezones
+            ("Tue, 05 Mar 2024 10:00:00 EST", "2024-03-05T10:00:00-05:00"),
+            ("Tue, 05 Mar 2024 10:00:00 PDT", "2024-03-05T10:00:00-07:00"),
+            (
+                "Tue, 05 Mar 2024 10:00:00 CEST",
+                "2024-03-05T10:00:00+02:00",
+            ),
+            (
+                "Tue, 05 Mar 2024 10:00:00 GMT+2",
+                "2024-03-05T10:00:00+02:00",
+            ),
+            (
+                "Tue, 05 Mar 2024 10:00:00 UTC+05:30",
+                "2024-03-05T10:00:00+05:30",
+            ),
+            (
+                "Tue, 05 Mar 2024 10:00:00 +0000 (UTC)",
+                "2024-03-05T10:00:00+00:00",
+            ),
+            // Two digit years
+            ("Tue, 05 Mar 24 10:00:00 +0000", "2024-03-05T10:00:00+00:00"),
+            // ISO 8601
+            ("2024-03-05T10:00:00Z", "2024-03-05T10:00:00+00:00"),
+            (
+                "2024-03-05T10:00:00.123+01:00",
+                "2024-03-05T10:00:00.123+01:00",
+            ),
+            ("2024-03-05T10:00+01:00", "2024-03-05T10:00:00+01:00"),
+            ("2024-03-05 10:00:00 +0100", "2024-03-05T10:00:00+01:00"),
+            ("2024-03-05T10:00:00", "2024-03-05T10:00:00+00:00"),
+            ("2024-03-05 10:00:00", "2024-03-05T10:00:00+00:00"),
+            ("2024-03-05", "2024-03-05T00:00:00+00:00"),
+            // US style
+            ("Mar 05, 2024 10:00:00 -0500", "2024-03-05T10:00:00-05:00"),
+            ("March 5, 2024 10:00 AM EST", "2024-03-05T10:00:00-05:00"),
+            (
+                "Tuesday, March 5, 2024 3:30 PM",
+                "2024-03-05T15:30:00+00:00",
+            ),
+            ("March 5, 2024", "2024-03-05T00:00:00+00:00"),
+            // Localized
+            (
+                "Di, 05 Mär 2024 10:00:00 +0100",
+                "2024-03-05T10:00:00+01:00",
+            ),
+            (
+                "mar., 05 mars 2024 10:00:00 +0100",
+                "2024-03-05T10:00:00+01:00",
+            ),
+            ("05 Dezember 2024 10:00:00 MEZ", "2024-12-05T10:00:00+01:00"),
+            (
+                "jue, 05 dic 2024 10:00:00 +0100",
+                "2024-12-05T10:00:00+01:00",
+            ),
+            ("05 ottobre 2024", "2024-10-05T00:00:00+00:00"),
+            ("05 mei 2024 10:00:00 +0200", "2024-05-05T10:00:00+02:00"),
+            // Surrounding whitespace
+            (
+                "  Tue, 05 Mar 2024 10:00:00 +0000\n",
+                "2024-03-05T10:00:00+00:00",
+            ),
+        ];
+
+        for (input, expected) in corpus {
+            let expected = DateTime::parse_from_rfc3339(expected).unwrap();
+            assert_eq!(parse_date(input), Some(expected), "parsing {input:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for input in [
+            "",
+            "yesterday",
+            "not a date",
+            "32 Mar 2024",
+            "2024-13-01",
+            "Mon, 01 Apr 2024 10:00:00 GMT+éa",
+        ] {
+            assert_eq!(parse_date(input), None, "parsing {input:?}");
+        }
+    }
+
+    #[test]
+    fn parses_rss_fallback_dates() {
+        let channel = rss::Channel::read_from(
+            br#"<?xml version="1.0"?>
+            <rss version="2.0"
+                xmlns:dc="http://purl.org/dc/elements/1.1/"
+                xmlns:a10="http://www.w3.org/2005/Atom">
+              <channel>
+                <title>Test</title>
+                <item>
+                  <title>Dublin Core</title>
+                  <dc:date>2024-03-05T10:00:00Z</dc:date>
+                </item>
+                <item>
+                  <title>Atom</title>
+                  <a10:updated>2024-03-06T10:00:00Z</a10:updated>
+                </item>
+                <item>
+                  <title>Broken</title>
+                  <pubDate>sometime</pubDate>
+                </item>
+              </channel>
+            </rss>"# as &[u8],
+        )
+        .unwrap();
+
+        let dates = channel
+            .items
+            .iter()
+            .map(|item| rss_item_published(item, &channel.namespaces).map(|d| d.to_rfc3339()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            dates,
+            [
+                Some("2024-03-05T10:00:00+00:00".to_owned()),
+                Some("2024-03-06T10:00:00+00:00".to_owned()),
+                None,
+            ]
+        );
+
+        assert_eq!(
+            rss_item_updated(&channel.items[1], &channel.namespaces).map(|d| d.to_rfc3339()),
+            Some("2024-03-06T10:00:00+00:00".to_owned())
+        );
+    }
+}
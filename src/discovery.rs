@@ -0,0 +1,73 @@
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Serialize;
+
+/// Paths commonly used for feeds by blogging platforms and static site
+/// generators, tried when a page doesn't advertise its feeds.
+pub const COMMON_FEED_PATHS: &[&str] = &[
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.json",
+];
+
+const FEED_MIME_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FeedCandidate {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Finds feeds advertised by an HTML page through
+/// `<link rel="alternate" type="...">` tags.
+pub fn find_feed_links(html: &str, base: &Url) -> Vec<FeedCandidate> {
+    let html = Html::parse_document(html);
+    let selector = Selector::parse("link[rel~=\"alternate\"][type][href]").unwrap();
+
+    let mut candidates: Vec<FeedCandidate> = vec![];
+
+    for link in html.select(&selector) {
+        let mime_type = link.attr("type").unwrap_or_default().trim();
+        if !FEED_MIME_TYPES
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(mime_type))
+        {
+            continue;
+        }
+
+        let Some(url) = link.attr("href").and_then(|href| base.join(href).ok()) else {
+            continue;
+        };
+
+        if candidates.iter().any(|c| c.url == url.as_str()) {
+            continue;
+        }
+
+        candidates.push(FeedCandidate {
+            url: url.into(),
+            title: link.attr("title").map(ToOwned::to_owned),
+        });
+    }
+
+    candidates
+}
+
+/// Returns true if a response looks like an HTML page rather than a feed.
+pub fn is_html(content_type: Option<&str>, content: &[u8]) -> bool {
+    if content_type.is_some_and(|t| t.contains("html")) {
+        return true;
+    }
+
+    let start = String::from_utf8_lossy(&content[..content.len().min(512)]).to_ascii_lowercase();
+    let start = start.trim_start();
+
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
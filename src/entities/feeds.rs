@@ -13,12 +13,47 @@ pub struct Model {
     pub icon: Option<String>,
     pub thumbnail: Option<String>,
     pub last_synced_at: Option<i64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub sync_interval: Option<i64>,
+    pub next_sync_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+    pub consecutive_failures: i32,
+    pub last_success_at: Option<i64>,
+    pub fetch_full_content: bool,
+    pub is_podcast: bool,
+    pub active: bool,
+    pub retention_max_age_days: Option<i64>,
+    pub retention_keep_last: Option<i64>,
+    pub auth_username: Option<String>,
+    pub auth_password: Option<String>,
+    pub auth_token: Option<String>,
+    pub user_agent: Option<String>,
+    pub cookie: Option<String>,
+    pub headers: Option<Json>,
+    pub proxy: Option<String>,
+    pub accept_invalid_certs: bool,
+    pub min_sync_interval: Option<i64>,
+    pub skip_hours: Option<Json>,
+    pub skip_days: Option<Json>,
+    pub site_images_checked_at: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::post_tombstones::Entity")]
+    PostTombstones,
     #[sea_orm(has_many = "super::posts::Entity")]
     Posts,
+    #[sea_orm(has_many = "super::previous_feed_urls::Entity")]
+    PreviousFeedUrls,
+}
+
+impl Related<super::post_tombstones::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostTombstones.def()
+    }
 }
 
 impl Related<super::posts::Entity> for Entity {
@@ -27,4 +62,10 @@ impl Related<super::posts::Entity> for Entity {
     }
 }
 
+impl Related<super::previous_feed_urls::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PreviousFeedUrls.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,6 +2,11 @@
 
 pub mod prelude;
 
+pub mod enclosures;
 pub mod feeds;
+pub mod playback_progress;
+pub mod post_revisions;
+pub mod post_tombstones;
 pub mod posts;
+pub mod previous_feed_urls;
 pub mod push_subscriptions;
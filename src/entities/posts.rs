@@ -8,13 +8,17 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub feed_id: Uuid,
-    #[sea_orm(unique)]
-    pub url: String,
+    pub guid: String,
+    pub url: Option<String>,
     pub title: String,
     pub publish_time: String,
     pub description: Option<String>,
     pub content: Option<String>,
     pub thumbnail: Option<String>,
+    pub updated_time: Option<String>,
+    pub author: Option<String>,
+    pub first_seen_at: Option<i64>,
+    pub starred: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,6 +31,18 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Feeds,
+    #[sea_orm(has_many = "super::enclosures::Entity")]
+    Enclosures,
+    #[sea_orm(has_one = "super::playback_progress::Entity")]
+    PlaybackProgress,
+    #[sea_orm(has_many = "super::post_revisions::Entity")]
+    PostRevisions,
+}
+
+impl Related<super::enclosures::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Enclosures.def()
+    }
 }
 
 impl Related<super::feeds::Entity> for Entity {
@@ -35,4 +51,16 @@ impl Related<super::feeds::Entity> for Entity {
     }
 }
 
+impl Related<super::playback_progress::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PlaybackProgress.def()
+    }
+}
+
+impl Related<super::post_revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostRevisions.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,10 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.20
 
+pub use super::enclosures::Entity as Enclosures;
 pub use super::feeds::Entity as Feeds;
+pub use super::playback_progress::Entity as PlaybackProgress;
+pub use super::post_revisions::Entity as PostRevisions;
+pub use super::post_tombstones::Entity as PostTombstones;
 pub use super::posts::Entity as Posts;
+pub use super::previous_feed_urls::Entity as PreviousFeedUrls;
 pub use super::push_subscriptions::Entity as PushSubscriptions;
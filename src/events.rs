@@ -0,0 +1,94 @@
+use futures::{Stream, StreamExt, stream};
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+
+/// How many events can be buffered for a slow client before it starts
+/// missing them.
+const CAPACITY: usize = 256;
+
+/// Something that happened on the server which connected clients may want to
+/// react to.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    PostCreated {
+        id: String,
+        feed_id: String,
+        title: String,
+        url: Option<String>,
+        post_time: String,
+    },
+    FeedSynced {
+        feed_id: String,
+        new_posts: u64,
+    },
+    FeedError {
+        feed_id: String,
+        error: String,
+    },
+    FeedAdded {
+        feed_id: String,
+        title: String,
+        url: String,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PostCreated { .. } => "post_created",
+            Event::FeedSynced { .. } => "feed_synced",
+            Event::FeedError { .. } => "feed_error",
+            Event::FeedAdded { .. } => "feed_added",
+        }
+    }
+}
+
+/// Broadcasts events to every connected client.
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+    closed: watch::Sender<bool>,
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events {
+            sender: broadcast::channel(CAPACITY).0,
+            closed: watch::Sender::new(false),
+        }
+    }
+
+    /// Sends an event to all current subscribers. Events published while
+    /// nobody is listening are dropped.
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to events published from now on. The stream ends when
+    /// [`Events::close`] is called.
+    pub fn subscribe(&self) -> impl Stream<Item = Event> + use<> {
+        let mut closed = self.closed.subscribe();
+
+        stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("event subscriber lagged behind, skipped {skipped} events");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .take_until(async move {
+            let _ = closed.wait_for(|closed| *closed).await;
+        })
+    }
+
+    /// Ends all subscriptions, so that open connections don't hold up a
+    /// graceful shutdown.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode, Url};
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How long to back off after a `429` that doesn't say how long to wait.
+const DEFAULT_RETRY_AFTER: TimeDelta = TimeDelta::minutes(1);
+/// Upper bound on how long a `Retry-After` header can block a host for.
+const MAX_RETRY_AFTER: TimeDelta = TimeDelta::days(1);
+
+#[derive(Debug, Error)]
+#[error("{host} is rate limited until {until}")]
+pub struct RateLimited {
+    pub host: String,
+    pub until: DateTime<Utc>,
+}
+
+struct HostState {
+    semaphore: Arc<Semaphore>,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+/// Limits the number of concurrent requests made to each host, and keeps
+/// track of hosts that have asked us to back off.
+pub struct HostLimiter {
+    max_per_host: usize,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl HostLimiter {
+    pub fn new(max_per_host: usize) -> HostLimiter {
+        HostLimiter {
+            max_per_host,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for a free request slot for the url's host. The slot is released
+    /// when the returned permit is dropped.
+    ///
+    /// Fails immediately if the host is currently backing off.
+    pub async fn acquire(&self, url: &Url) -> Result<OwnedSemaphorePermit, RateLimited> {
+        let host = url.host_str().unwrap_or_default();
+
+        let semaphore = {
+            let mut hosts = self.hosts.lock();
+            let state = self.host_state(&mut hosts, host);
+
+            if let Some(until) = state.blocked_until {
+                if until > Utc::now() {
+                    return Err(RateLimited {
+                        host: host.to_owned(),
+                        until,
+                    });
+                }
+                state.blocked_until = None;
+            }
+
+            state.semaphore.clone()
+        };
+
+        Ok(semaphore
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed"))
+    }
+
+    fn host_state<'a>(
+        &self,
+        hosts: &'a mut HashMap<String, HostState>,
+        host: &str,
+    ) -> &'a mut HostState {
+        hosts.entry(host.to_owned()).or_insert_with(|| HostState {
+            semaphore: Arc::new(Semaphore::new(self.max_per_host)),
+            blocked_until: None,
+        })
+    }
+
+    /// Checks a response for `429 Too Many Requests` or `503 Service
+    /// Unavailable`, and if found blocks further requests to the host for as
+    /// long as the server asked.
+    pub fn check_response(&self, res: &Response) -> Result<(), RateLimited> {
+        let retry_after = res.headers().get(RETRY_AFTER).and_then(|v| v.to_str().ok());
+
+        self.check_status(
+            res.url().host_str().unwrap_or_default(),
+            res.status(),
+            retry_after,
+        )
+    }
+
+    fn check_status(
+        &self,
+        host: &str,
+        status: StatusCode,
+        retry_after: Option<&str>,
+    ) -> Result<(), RateLimited> {
+        let retry_after = retry_after.and_then(parse_retry_after);
+
+        let retry_after = match status {
+            StatusCode::TOO_MANY_REQUESTS => retry_after.unwrap_or(DEFAULT_RETRY_AFTER),
+            StatusCode::SERVICE_UNAVAILABLE => match retry_after {
+                Some(retry_after) => retry_after,
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let host = host.to_owned();
+        let until = Utc::now() + retry_after.clamp(TimeDelta::zero(), MAX_RETRY_AFTER);
+
+        tracing::warn!("{host} responded with {status}, backing off until {until}");
+
+        self.host_state(&mut self.hosts.lock(), &host).blocked_until = Some(until);
+
+        Err(RateLimited { host, until })
+    }
+}
+
+/// Parses a `Retry-After` value, which is either a number of seconds or an
+/// HTTP date.
+fn parse_retry_after(value: &str) -> Option<TimeDelta> {
+    if let Ok(seconds) = value.trim().parse::<i64>() {
+        // Clamped up front, as a huge delay would be out of range.
+        return TimeDelta::try_seconds(seconds.clamp(0, MAX_RETRY_AFTER.num_seconds()));
+    }
+
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.to_utc() - Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{host}/feed.xml")).unwrap()
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(TimeDelta::seconds(120)));
+        assert_eq!(parse_retry_after(" 5 "), Some(TimeDelta::seconds(5)));
+        assert_eq!(parse_retry_after("-5"), Some(TimeDelta::zero()));
+        assert_eq!(
+            parse_retry_after("99999999999999999"),
+            Some(MAX_RETRY_AFTER)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let date = (Utc::now() + TimeDelta::hours(1)).to_rfc2822();
+        let retry_after = parse_retry_after(&date).unwrap();
+        assert!(retry_after > TimeDelta::minutes(59) && retry_after <= TimeDelta::hours(1));
+    }
+
+    #[tokio::test]
+    async fn blocks_rate_limited_hosts() {
+        let limiter = HostLimiter::new(2);
+
+        assert!(
+            limiter
+                .check_status("example.com", StatusCode::OK, Some("60"))
+                .is_ok()
+        );
+
+        // A 503 only means backing off when the server says for how long.
+        assert!(
+            limiter
+                .check_status("example.com", StatusCode::SERVICE_UNAVAILABLE, None)
+                .is_ok()
+        );
+
+        let blocked = limiter
+            .check_status("example.com", StatusCode::TOO_MANY_REQUESTS, None)
+            .unwrap_err();
+        assert_eq!(blocked.host, "example.com");
+        assert!(blocked.until > Utc::now() + DEFAULT_RETRY_AFTER - TimeDelta::seconds(5));
+
+        let err = limiter.acquire(&url("example.com")).await.unwrap_err();
+        assert_eq!(err.until, blocked.until);
+
+        // Other hosts are unaffected.
+        assert!(limiter.acquire(&url("example.org")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unblocks_hosts_once_the_delay_has_passed() {
+        let limiter = HostLimiter::new(2);
+
+        limiter
+            .check_status("example.com", StatusCode::SERVICE_UNAVAILABLE, Some("0"))
+            .unwrap_err();
+
+        assert!(limiter.acquire(&url("example.com")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn releases_permits_when_dropped() {
+        let limiter = HostLimiter::new(1);
+
+        let permit = limiter.acquire(&url("example.com")).await.unwrap();
+
+        let second = tokio::time::timeout(
+            Duration::from_millis(50),
+            limiter.acquire(&url("example.com")),
+        )
+        .await;
+        assert!(second.is_err(), "second request should wait for the first");
+
+        // The limit is per host.
+        assert!(limiter.acquire(&url("example.org")).await.is_ok());
+
+        drop(permit);
+
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            limiter.acquire(&url("example.com")),
+        )
+        .await;
+        assert!(matches!(third, Ok(Ok(_))));
+    }
+}
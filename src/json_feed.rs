@@ -0,0 +1,100 @@
+//! Types for [JSON Feed](https://www.jsonfeed.org/version/1.1/) documents.
+//!
+//! Only the fields that Tress makes use of are included. Version 1.0 and 1.1
+//! documents share the same shape for all of them.
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Feed {
+    pub version: String,
+    pub title: String,
+    pub home_page_url: Option<String>,
+    pub icon: Option<String>,
+    pub favicon: Option<String>,
+    #[serde(default)]
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Item {
+    pub id: IdValue,
+    pub url: Option<String>,
+    pub external_url: Option<String>,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub content_html: Option<String>,
+    pub content_text: Option<String>,
+    pub image: Option<String>,
+    /// Dates are meant to be RFC 3339, but are parsed leniently along with
+    /// those of other formats.
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Version 1.1 replaced `author` with `authors`.
+    pub author: Option<Author>,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Author {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Attachment {
+    pub url: String,
+    pub mime_type: String,
+    pub size_in_bytes: Option<i64>,
+    pub duration_in_seconds: Option<f64>,
+}
+
+/// Item ids are meant to be strings, but some feeds in the wild use numbers.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum IdValue {
+    String(String),
+    Number(serde_json::Number),
+}
+
+impl IdValue {
+    pub fn into_string(self) -> String {
+        match self {
+            IdValue::String(id) => id,
+            IdValue::Number(id) => id.to_string(),
+        }
+    }
+}
+
+impl Feed {
+    pub fn read_from(content: &[u8]) -> serde_json::Result<Feed> {
+        let feed: Feed = serde_json::from_slice(content)?;
+
+        if !feed.version.starts_with("https://jsonfeed.org/version/") {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported JSON Feed version: {}",
+                feed.version
+            )));
+        }
+
+        Ok(feed)
+    }
+}
+
+impl Item {
+    pub fn author_name(&self) -> Option<&str> {
+        self.authors
+            .iter()
+            .chain(&self.author)
+            .find_map(|author| author.name.as_deref())
+    }
+
+    /// The first image attachment, for items that don't set `image`.
+    pub fn image_attachment(&self) -> Option<&Attachment> {
+        self.attachments
+            .iter()
+            .find(|attachment| attachment.mime_type.starts_with("image/"))
+    }
+}
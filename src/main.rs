@@ -1,36 +1,60 @@
 mod auth_middleware;
+mod charset;
 mod config;
+mod dates;
+mod discovery;
 mod entities;
+mod events;
+mod fetch;
+mod host_limiter;
+mod json_feed;
 mod jwks;
-
+mod parser;
+mod podcast;
+mod rdf;
+mod readability;
+mod retention;
+mod schedule;
+mod site_metadata;
+mod sync_jobs;
+mod thumbnails;
+
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use axum::extract::{self, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::StatusCode;
+use axum::http::header::{
+    CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION,
+};
+use axum::response::sse::{self, KeepAlive, Sse};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{any, get, post};
 use axum::{Json, Router};
 use backon::{ExponentialBuilder, Retryable};
 use base64ct::{Base64UrlUnpadded, Encoding};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, TimeDelta, Utc};
 use eyre::eyre;
+use futures::{Stream, StreamExt, stream};
 use itertools::Itertools;
 use migration::{Migrator, MigratorTrait, OnConflict};
-use reqwest::{Client, Request};
-use scraper::{Html, Selector};
-use sea_orm::prelude::Uuid;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Client, Method, Request, Url};
+use scraper::Html;
+use sea_orm::prelude::{Expr, Uuid};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectOptions, Database, DatabaseConnection,
-    DbErr, EntityTrait, IntoActiveModel, ModelTrait, QueryFilter, QueryOrder, SqlErr,
-    TransactionTrait,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectOptions, Database,
+    DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, LoaderTrait, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait, SqlErr, TransactionTrait,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::json;
 use thiserror::Error;
 use tokio::signal;
-use tokio::sync::mpsc;
+use tokio::sync::{OwnedSemaphorePermit, mpsc};
 use tower_http::services::{ServeDir, ServeFile};
 use tracing::{debug, error, trace};
 use tracing_subscriber::layer::SubscriberExt;
@@ -40,15 +64,31 @@ use web_push_native::p256::PublicKey;
 use web_push_native::{Auth, WebPushBuilder};
 
 use crate::config::{Config, OidcConfig};
+use crate::discovery::FeedCandidate;
 use crate::entities::prelude::*;
-use crate::entities::{feeds, posts, push_subscriptions};
+use crate::entities::{
+    enclosures, feeds, playback_progress, post_revisions, post_tombstones, posts,
+    previous_feed_urls, push_subscriptions,
+};
+use crate::events::{Event, Events};
+use crate::fetch::{
+    Blocked, BodyTooLarge, ClientPool, FeedAuth, FeedClients, FetchPolicy, RequestOptions,
+};
+use crate::host_limiter::{HostLimiter, RateLimited};
 use crate::jwks::JwksClient;
+use crate::parser::{FeedEntry, ParsedFeed};
+use crate::schedule::FeedHints;
+use crate::sync_jobs::{SyncJob, SyncJobStatus, SyncJobs};
 
 #[derive(Clone)]
 struct App {
     db: DatabaseConnection,
     sync_sender: mpsc::UnboundedSender<SyncRequest>,
-    http_client: Client,
+    sync_jobs: Arc<SyncJobs>,
+    events: Arc<Events>,
+    clients: Arc<ClientPool>,
+    host_limiter: Arc<HostLimiter>,
+    fetch_policy: Arc<FetchPolicy>,
     vapid_key: Arc<ES256KeyPair>,
     oidc_config: Option<OidcConfig>,
 }
@@ -86,22 +126,29 @@ async fn main() -> eyre::Result<()> {
             loop {
                 sync_sender
                     .send(SyncRequest {
-                        scope: SyncScope::All,
+                        scope: SyncScope::Due,
                         notify: true,
+                        job: None,
                     })
                     .unwrap();
-                tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+                tokio::time::sleep(Duration::from_secs(60)).await;
             }
         }
     });
 
-    let http_client = Client::builder()
-        .default_headers({
-            let mut headers = HeaderMap::new();
-            headers.insert("User-Agent", "Tress".parse()?);
-            headers
-        })
-        .build()?;
+    tokio::spawn(retention::run_retention_worker(
+        db.clone(),
+        config.retention,
+    ));
+
+    let fetch_policy = Arc::new(FetchPolicy::new(&config.fetch));
+
+    // Used for the OIDC provider, which is configured by the admin rather
+    // than by users.
+    let http_client =
+        fetch::client_builder(&config.fetch, config.fetch.proxy.as_deref())?.build()?;
+
+    let clients = Arc::new(ClientPool::new(config.fetch.clone(), fetch_policy.clone())?);
 
     let oidc_config = config.oidc.clone();
 
@@ -113,24 +160,58 @@ async fn main() -> eyre::Result<()> {
         None
     };
 
+    let host_limiter = Arc::new(HostLimiter::new(config.sync.per_host_concurrency));
+
+    let sync_jobs = Arc::new(SyncJobs::new());
+    let events = Arc::new(Events::new());
+
+    // Push endpoints come from users' browsers, so are held to the same
+    // rules as feeds.
     let push_client = PushClient {
-        http_client: http_client.clone(),
+        http_client: fetch::guarded_client_builder(
+            &config.fetch,
+            &fetch_policy,
+            config.fetch.proxy.as_deref(),
+            None,
+        )?
+        .build()?,
+        fetch_policy: fetch_policy.clone(),
         vapid_key: vapid_key.clone(),
     };
 
     tokio::spawn(run_sync_worker(
         sync_receiver,
-        http_client.clone(),
-        db.clone(),
-        push_client,
+        SyncWorker {
+            clients: clients.clone(),
+            host_limiter: host_limiter.clone(),
+            fetch_policy: fetch_policy.clone(),
+            db: db.clone(),
+            push_client,
+            sync_jobs: sync_jobs.clone(),
+            events: events.clone(),
+            concurrency: config.sync.concurrency,
+        },
     ));
 
     let protected_api = Router::new()
         .route("/push_subscriptions", post(create_push_subscription))
         .route("/feeds", get(get_feeds).post(add_feed))
-        .route("/feeds/{id}", get(get_feed).delete(delete_feed))
+        .route(
+            "/feeds/{id}",
+            get(get_feed).patch(update_feed).delete(delete_feed),
+        )
         .route("/posts", get(get_posts))
-        .route("/posts/{id}", get(get_post))
+        .route("/posts/{id}", get(get_post).patch(update_post))
+        .route("/posts/{id}/revisions", get(get_post_revisions))
+        .route(
+            "/posts/{id}/playback",
+            get(get_playback).put(update_playback),
+        )
+        .route("/continue_listening", get(get_continue_listening))
+        .route("/feeds/{id}/sync", post(trigger_feed_sync))
+        .route("/sync", post(trigger_sync))
+        .route("/sync/jobs/{id}", get(get_sync_job))
+        .route("/events", get(get_events))
         .fallback(any((
             StatusCode::NOT_FOUND,
             Json(json!({"message": "not found"})),
@@ -146,7 +227,11 @@ async fn main() -> eyre::Result<()> {
         .with_state(App {
             db: db.clone(),
             sync_sender,
-            http_client,
+            sync_jobs,
+            events: events.clone(),
+            clients,
+            host_limiter,
+            fetch_policy,
             vapid_key,
             oidc_config,
         });
@@ -160,7 +245,10 @@ async fn main() -> eyre::Result<()> {
     tracing::info!("server listening at http://{}", listener.local_addr()?);
 
     axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            events.close();
+        })
         .await?;
 
     db.close().await?;
@@ -171,6 +259,7 @@ async fn main() -> eyre::Result<()> {
 #[derive(Clone)]
 struct PushClient {
     http_client: Client,
+    fetch_policy: Arc<FetchPolicy>,
     vapid_key: Arc<ES256KeyPair>,
 }
 
@@ -180,6 +269,9 @@ impl PushClient {
         subscription: &push_subscriptions::Model,
         message: &impl serde::Serialize,
     ) -> eyre::Result<bool> {
+        let endpoint = Url::parse(&subscription.endpoint)?;
+        self.fetch_policy.check_url(&endpoint)?;
+
         let req = WebPushBuilder::new(
             subscription.endpoint.parse()?,
             PublicKey::from_sec1_bytes(&Base64UrlUnpadded::decode_vec(&subscription.p256dh_key)?)?,
@@ -227,7 +319,18 @@ struct PushSubscriptionKeys {
     p256dh: String,
 }
 
-async fn create_push_subscription(State(app): State<App>, Json(body): Json<PushSubscriptionReq>) {
+async fn create_push_subscription(
+    State(app): State<App>,
+    Json(body): Json<PushSubscriptionReq>,
+) -> Result<(), ApiError> {
+    let Ok(endpoint) = Url::parse(&body.subscription.endpoint) else {
+        return Err(ApiError::BadRequest("invalid push endpoint"));
+    };
+
+    if app.fetch_policy.check_url(&endpoint).is_err() {
+        return Err(ApiError::BadRequest("push endpoint is not allowed"));
+    }
+
     let subscription = push_subscriptions::ActiveModel {
         id: ActiveValue::NotSet,
         endpoint: ActiveValue::Set(body.subscription.endpoint),
@@ -242,8 +345,9 @@ async fn create_push_subscription(State(app): State<App>, Json(body): Json<PushS
                 .to_owned(),
         )
         .exec(&app.db)
-        .await
-        .unwrap();
+        .await?;
+
+    Ok(())
 }
 
 async fn get_config(State(app): State<App>) -> impl IntoResponse {
@@ -269,7 +373,10 @@ async fn get_config(State(app): State<App>) -> impl IntoResponse {
 }
 
 enum ApiError {
+    BadRequest(&'static str),
     NotFound,
+    /// A remote server couldn't be reached or gave an unusable response.
+    BadGateway(String),
     Internal,
 }
 
@@ -290,7 +397,13 @@ impl From<eyre::Report> for ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         match self {
+            Self::BadRequest(message) => {
+                (StatusCode::BAD_REQUEST, Json(json!({ "message": message }))).into_response()
+            }
             Self::NotFound => StatusCode::NOT_FOUND.into_response(),
+            Self::BadGateway(message) => {
+                (StatusCode::BAD_GATEWAY, Json(json!({ "message": message }))).into_response()
+            }
             Self::Internal => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
         }
     }
@@ -301,21 +414,101 @@ struct FeedResponse {
     id: String,
     title: String,
     url: String,
+    icon: Option<String>,
+    thumbnail: Option<String>,
     last_synced_at: Option<i64>,
+    sync_interval: Option<i64>,
+    next_sync_at: Option<i64>,
+    last_success_at: Option<i64>,
+    last_error: Option<String>,
+    last_error_at: Option<i64>,
+    consecutive_failures: i32,
+    /// Whether the feed has been failing for long enough that it probably
+    /// needs attention.
+    broken: bool,
+    fetch_full_content: bool,
+    is_podcast: bool,
+    /// Whether the feed is still being synced. Feeds that report they are
+    /// gone for good are deactivated.
+    active: bool,
+    /// Urls the feed was previously at, before permanently redirecting to
+    /// its current url.
+    previous_urls: Vec<String>,
+    /// Overrides for the default retention policy, where 0 means no limit.
+    retention_max_age_days: Option<i64>,
+    retention_keep_last: Option<i64>,
+    /// How requests for the feed are authenticated. Passwords and tokens are
+    /// never returned.
+    auth: Option<FeedAuthResponse>,
+    user_agent: Option<String>,
+    has_cookie: bool,
+    /// Names of the extra headers sent with requests. Their values are left
+    /// out, as they often hold credentials.
+    headers: Vec<String>,
+    /// The feed's own proxy, without its password.
+    proxy: Option<String>,
+    /// Whether certificates aren't being verified for the feed's host,
+    /// leaving requests to it open to interception.
+    danger_accept_invalid_certs: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FeedAuthResponse {
+    Basic { username: String },
+    Bearer,
+}
+
+impl From<(feeds::Model, Vec<previous_feed_urls::Model>)> for FeedResponse {
+    fn from((feed, previous_urls): (feeds::Model, Vec<previous_feed_urls::Model>)) -> Self {
+        let options = RequestOptions::from_feed(&feed);
+
+        FeedResponse {
+            id: feed.id.to_string(),
+            title: feed.title,
+            url: feed.url,
+            icon: feed.icon,
+            thumbnail: feed.thumbnail,
+            last_synced_at: feed.last_synced_at,
+            sync_interval: feed.sync_interval,
+            next_sync_at: feed.next_sync_at,
+            last_success_at: feed.last_success_at,
+            last_error: feed.last_error,
+            last_error_at: feed.last_error_at,
+            consecutive_failures: feed.consecutive_failures,
+            broken: feed.consecutive_failures >= schedule::BROKEN_AFTER_FAILURES,
+            fetch_full_content: feed.fetch_full_content,
+            is_podcast: feed.is_podcast,
+            active: feed.active,
+            previous_urls: previous_urls.into_iter().map(|url| url.url).collect(),
+            retention_max_age_days: feed.retention_max_age_days,
+            retention_keep_last: feed.retention_keep_last,
+            auth: options.auth.map(|auth| match auth {
+                FeedAuth::Basic { username, .. } => FeedAuthResponse::Basic { username },
+                FeedAuth::Bearer { .. } => FeedAuthResponse::Bearer,
+            }),
+            user_agent: options.user_agent,
+            has_cookie: options.cookie.is_some(),
+            headers: options.headers.into_keys().collect(),
+            proxy: options.proxy.map(|proxy| match Url::parse(&proxy) {
+                Ok(mut url) if url.password().is_some() => {
+                    let _ = url.set_password(None);
+                    url.into()
+                }
+                _ => proxy,
+            }),
+            danger_accept_invalid_certs: options.accept_invalid_certs,
+        }
+    }
 }
 
 async fn get_feeds(State(app): State<App>) -> Result<impl IntoResponse, ApiError> {
-    let feeds = Feeds::find().all(&app.db).await?;
+    let feeds = Feeds::find()
+        .find_with_related(PreviousFeedUrls)
+        .all(&app.db)
+        .await?;
     Ok(Json(
-        feeds
-            .into_iter()
-            .map(|feed| FeedResponse {
-                id: feed.id.to_string(),
-                title: feed.title,
-                url: feed.url,
-                last_synced_at: feed.last_synced_at,
-            })
-            .collect_vec(),
+        feeds.into_iter().map(FeedResponse::from).collect_vec(),
     ))
 }
 
@@ -327,12 +520,204 @@ async fn get_feed(
         .one(&app.db)
         .await?
         .ok_or(ApiError::NotFound)?;
-    Ok(Json(FeedResponse {
-        id: feed.id.to_string(),
-        title: feed.title,
-        url: feed.url,
-        last_synced_at: feed.last_synced_at,
-    }))
+    let previous_urls = feed.find_related(PreviousFeedUrls).all(&app.db).await?;
+    Ok(Json(FeedResponse::from((feed, previous_urls))))
+}
+
+#[derive(Deserialize)]
+struct UpdateFeedReq {
+    /// Sync interval in seconds, or `null` to go back to adaptive scheduling.
+    #[serde(default, deserialize_with = "double_option")]
+    sync_interval: Option<Option<i64>>,
+    /// Whether to extract the full article from each post's web page, for
+    /// feeds that only include summaries.
+    fetch_full_content: Option<bool>,
+    /// Days after which posts are pruned, 0 to keep them regardless of age,
+    /// or `null` to use the default.
+    #[serde(default, deserialize_with = "double_option")]
+    retention_max_age_days: Option<Option<i64>>,
+    /// Number of recent posts to keep, 0 to keep all of them, or `null` to
+    /// use the default.
+    #[serde(default, deserialize_with = "double_option")]
+    retention_keep_last: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "double_option")]
+    auth: Option<Option<FeedAuth>>,
+    #[serde(default, deserialize_with = "double_option")]
+    user_agent: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    cookie: Option<Option<String>>,
+    /// Replaces all extra headers.
+    headers: Option<BTreeMap<String, String>>,
+    /// Proxy to use instead of the default, `"direct"` to bypass it, or
+    /// `null` to use the default.
+    #[serde(default, deserialize_with = "double_option")]
+    proxy: Option<Option<String>>,
+    /// Skips certificate verification for requests to the feed's host.
+    danger_accept_invalid_certs: Option<bool>,
+}
+
+/// Distinguishes between a field that is missing (`None`) and one that is
+/// explicitly set to `null` (`Some(None)`).
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+async fn update_feed(
+    extract::Path(id): extract::Path<Uuid>,
+    State(app): State<App>,
+    Json(req): Json<UpdateFeedReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let feed = Feeds::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut feed = feed.into_active_model();
+
+    if let Some(sync_interval) = req.sync_interval {
+        if sync_interval.is_some_and(|interval| interval <= 0) {
+            return Err(ApiError::BadRequest("sync_interval must be positive"));
+        }
+
+        if sync_interval
+            .is_some_and(|interval| interval > schedule::MAX_USER_INTERVAL.num_seconds())
+        {
+            return Err(ApiError::BadRequest(
+                "sync_interval must not be longer than a year",
+            ));
+        }
+
+        feed.sync_interval = ActiveValue::Set(sync_interval);
+        // Sync again straight away so the new schedule takes effect.
+        feed.next_sync_at = ActiveValue::Set(None);
+    }
+
+    if let Some(fetch_full_content) = req.fetch_full_content {
+        feed.fetch_full_content = ActiveValue::Set(fetch_full_content);
+    }
+
+    if let Some(max_age_days) = req.retention_max_age_days {
+        if max_age_days.is_some_and(|days| days < 0) {
+            return Err(ApiError::BadRequest(
+                "retention_max_age_days must not be negative",
+            ));
+        }
+
+        if max_age_days.is_some_and(|days| days > retention::MAX_AGE_DAYS_LIMIT) {
+            return Err(ApiError::BadRequest(
+                "retention_max_age_days must not be longer than 100 years",
+            ));
+        }
+
+        feed.retention_max_age_days = ActiveValue::Set(max_age_days);
+    }
+
+    if let Some(keep_last) = req.retention_keep_last {
+        if keep_last.is_some_and(|count| count < 0) {
+            return Err(ApiError::BadRequest(
+                "retention_keep_last must not be negative",
+            ));
+        }
+
+        feed.retention_keep_last = ActiveValue::Set(keep_last);
+    }
+
+    validate_request_headers(
+        req.user_agent.as_ref().and_then(|v| v.as_deref()),
+        req.cookie.as_ref().and_then(|v| v.as_deref()),
+        req.headers.as_ref().unwrap_or(&BTreeMap::new()),
+    )?;
+
+    if let Some(auth) = req.auth {
+        set_feed_auth(&mut feed, auth);
+    }
+
+    if let Some(user_agent) = req.user_agent {
+        feed.user_agent = ActiveValue::Set(user_agent);
+    }
+
+    if let Some(cookie) = req.cookie {
+        feed.cookie = ActiveValue::Set(cookie);
+    }
+
+    if let Some(headers) = req.headers {
+        set_feed_headers(&mut feed, headers);
+    }
+
+    if let Some(proxy) = req.proxy {
+        validate_proxy(&app.fetch_policy, proxy.as_deref())?;
+
+        feed.proxy = ActiveValue::Set(proxy);
+    }
+
+    if let Some(accept_invalid_certs) = req.danger_accept_invalid_certs {
+        feed.accept_invalid_certs = ActiveValue::Set(accept_invalid_certs);
+    }
+
+    let feed = feed.update(&app.db).await?;
+    let previous_urls = feed.find_related(PreviousFeedUrls).all(&app.db).await?;
+
+    Ok(Json(FeedResponse::from((feed, previous_urls))))
+}
+
+fn validate_proxy(policy: &FetchPolicy, proxy: Option<&str>) -> Result<(), ApiError> {
+    let Some(proxy) = proxy else {
+        return Ok(());
+    };
+
+    if !fetch::is_valid_proxy(proxy) {
+        return Err(ApiError::BadRequest("invalid proxy"));
+    }
+
+    if policy.check_proxy(proxy).is_err() {
+        return Err(ApiError::BadRequest("proxy is not allowed"));
+    }
+
+    Ok(())
+}
+
+fn validate_request_headers(
+    user_agent: Option<&str>,
+    cookie: Option<&str>,
+    headers: &BTreeMap<String, String>,
+) -> Result<(), ApiError> {
+    let is_valid_value = |value: &str| HeaderValue::from_str(value).is_ok();
+
+    if !user_agent.is_none_or(is_valid_value) {
+        return Err(ApiError::BadRequest("invalid user_agent"));
+    }
+
+    if !cookie.is_none_or(is_valid_value) {
+        return Err(ApiError::BadRequest("invalid cookie"));
+    }
+
+    for (name, value) in headers {
+        if HeaderName::from_str(name).is_err() || !is_valid_value(value) {
+            return Err(ApiError::BadRequest("invalid header"));
+        }
+    }
+
+    Ok(())
+}
+
+fn set_feed_auth(feed: &mut feeds::ActiveModel, auth: Option<FeedAuth>) {
+    let (username, password, token) = match auth {
+        Some(FeedAuth::Basic { username, password }) => (Some(username), password, None),
+        Some(FeedAuth::Bearer { token }) => (None, None, Some(token)),
+        None => (None, None, None),
+    };
+
+    feed.auth_username = ActiveValue::Set(username);
+    feed.auth_password = ActiveValue::Set(password);
+    feed.auth_token = ActiveValue::Set(token);
+}
+
+fn set_feed_headers(feed: &mut feeds::ActiveModel, headers: BTreeMap<String, String>) {
+    feed.headers = ActiveValue::Set((!headers.is_empty()).then(|| json!(headers)));
 }
 
 async fn delete_feed(
@@ -346,11 +731,42 @@ async fn delete_feed(
         .await?
         .ok_or(ApiError::NotFound)?;
 
+    let feed_posts = Posts::find()
+        .select_only()
+        .column(posts::Column::Id)
+        .filter(posts::Column::FeedId.eq(id))
+        .into_query();
+
+    PostRevisions::delete_many()
+        .filter(post_revisions::Column::PostId.in_subquery(feed_posts.clone()))
+        .exec(&txn)
+        .await?;
+
+    Enclosures::delete_many()
+        .filter(enclosures::Column::PostId.in_subquery(feed_posts.clone()))
+        .exec(&txn)
+        .await?;
+
+    PlaybackProgress::delete_many()
+        .filter(playback_progress::Column::PostId.in_subquery(feed_posts))
+        .exec(&txn)
+        .await?;
+
     Posts::delete_many()
         .filter(posts::Column::FeedId.eq(id))
         .exec(&txn)
         .await?;
 
+    PreviousFeedUrls::delete_many()
+        .filter(previous_feed_urls::Column::FeedId.eq(id))
+        .exec(&txn)
+        .await?;
+
+    PostTombstones::delete_many()
+        .filter(post_tombstones::Column::FeedId.eq(id))
+        .exec(&txn)
+        .await?;
+
     feed.delete(&txn).await?;
 
     txn.commit().await?;
@@ -361,41 +777,268 @@ async fn delete_feed(
 #[derive(Deserialize)]
 struct CreateFeedReq {
     url: String,
+    auth: Option<FeedAuth>,
+    user_agent: Option<String>,
+    cookie: Option<String>,
+    /// Extra headers to send with each request.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Proxy to use instead of the default, or `"direct"` to bypass it.
+    proxy: Option<String>,
+    /// Skips certificate verification for requests to the feed's host.
+    #[serde(default)]
+    danger_accept_invalid_certs: bool,
+}
+
+/// Reports why a feed that is being added couldn't be fetched.
+fn feed_fetch_error(e: eyre::Report) -> ApiError {
+    if Blocked::caused(&e) {
+        return ApiError::BadRequest("feed url is not allowed");
+    }
+
+    debug!("{e:?}");
+    ApiError::BadGateway(format!("{e:#}"))
 }
 
 async fn add_feed(
     State(app): State<App>,
     Json(req): Json<CreateFeedReq>,
-) -> Result<impl IntoResponse, ApiError> {
-    let feed = fetch_feed(&app.http_client, &req.url).await?;
+) -> Result<Response, ApiError> {
+    let Ok(url) = Url::parse(&req.url) else {
+        return Err(ApiError::BadRequest("invalid feed url"));
+    };
 
-    let title = match feed {
-        Feed::Atom(feed) => feed.title.value,
-        Feed::Rss(channel) => channel.title,
+    if app.fetch_policy.check_url(&url).is_err() {
+        return Err(ApiError::BadRequest("feed url is not allowed"));
+    }
+
+    validate_request_headers(
+        req.user_agent.as_deref(),
+        req.cookie.as_deref(),
+        &req.headers,
+    )?;
+
+    validate_proxy(&app.fetch_policy, req.proxy.as_deref())?;
+
+    if let Some(existing) = existing_feed_response(&app, &req.url).await? {
+        return Ok(existing);
+    }
+
+    let options = RequestOptions {
+        host: url.host_str().map(ToOwned::to_owned),
+        https: url.scheme() == "https",
+        auth: req.auth,
+        user_agent: req.user_agent,
+        cookie: req.cookie,
+        headers: req.headers,
+        proxy: req.proxy,
+        accept_invalid_certs: req.danger_accept_invalid_certs,
+    };
+
+    // The url that was given for the feed, if it redirected elsewhere.
+    let mut moved_from = None;
+
+    let (url, feed) = match fetch_new_feed(&app, &options, &req.url).await {
+        Ok((url, feed)) => {
+            if url != req.url {
+                moved_from = Some(req.url);
+            }
+            (url, feed)
+        }
+        Err(e) => {
+            let page = match e.downcast::<HtmlPage>() {
+                Ok(page) => page,
+                Err(e) => return Err(feed_fetch_error(e)),
+            };
+            let mut candidates = discover_feeds(&app, &options, &page).await;
+
+            match candidates.len() {
+                0 => return Err(ApiError::BadRequest("no feeds found at the given url")),
+                1 => {
+                    let candidate = candidates.remove(0).url;
+                    let (url, feed) = fetch_new_feed(&app, &options, &candidate)
+                        .await
+                        .map_err(feed_fetch_error)?;
+                    if url != candidate {
+                        moved_from = Some(candidate);
+                    }
+                    (url, feed)
+                }
+                _ => {
+                    return Ok((
+                        StatusCode::MULTIPLE_CHOICES,
+                        Json(json!({
+                            "message": "multiple feeds found",
+                            "feeds": candidates,
+                        })),
+                    )
+                        .into_response());
+                }
+            }
+        }
     };
 
-    let feed = feeds::ActiveModel {
+    // The feed may have turned out to be one that has already been added
+    // under another url.
+    if let Some(existing) = existing_feed_response(&app, &url).await? {
+        return Ok(existing);
+    }
+
+    let images = resolve_feed_images(
+        &app.clients.get(&options)?,
+        &app.host_limiter,
+        &app.fetch_policy,
+        &options,
+        &Url::parse(&url).map_err(|e| eyre!(e))?,
+        &feed,
+        FeedImages::default(),
+    )
+    .await;
+
+    // Cache validators are left unset so that the first sync gets the full
+    // feed rather than a 304, and inserts its posts.
+    let mut feed = feeds::ActiveModel {
         id: ActiveValue::Set(Uuid::new_v4()),
-        title: ActiveValue::Set(title),
-        url: ActiveValue::Set(req.url),
+        title: ActiveValue::Set(feed.title.clone()),
+        icon: ActiveValue::Set(images.icon),
+        thumbnail: ActiveValue::Set(images.thumbnail),
+        site_images_checked_at: ActiveValue::Set(images.site_checked_at),
+        url: ActiveValue::Set(url),
+        is_podcast: ActiveValue::Set(feed.is_podcast),
+        user_agent: ActiveValue::Set(options.user_agent),
+        cookie: ActiveValue::Set(options.cookie),
+        proxy: ActiveValue::Set(options.proxy),
+        accept_invalid_certs: ActiveValue::Set(options.accept_invalid_certs),
         ..Default::default()
     };
 
-    let feed = feed.insert(&app.db).await?;
+    set_feed_auth(&mut feed, options.auth);
+    set_feed_headers(&mut feed, options.headers);
+
+    let txn = app.db.begin().await?;
+
+    let feed = feed.insert(&txn).await?;
+
+    let previous_url = match moved_from {
+        Some(url) => Some(
+            previous_feed_urls::ActiveModel {
+                id: ActiveValue::NotSet,
+                feed_id: ActiveValue::Set(feed.id),
+                url: ActiveValue::Set(url),
+                moved_at: ActiveValue::Set(Utc::now().timestamp()),
+            }
+            .insert(&txn)
+            .await?,
+        ),
+        None => None,
+    };
+
+    txn.commit().await?;
+
+    tracing::info!("added feed: {}", feed.url);
 
-    tracing::info!("added feed: {feed:?}");
+    app.events.publish(Event::FeedAdded {
+        feed_id: feed.id.to_string(),
+        title: feed.title.clone(),
+        url: feed.url.clone(),
+    });
 
     let _ = app.sync_sender.send(SyncRequest {
         scope: SyncScope::Feed(feed.id),
         notify: false,
+        job: None,
     });
 
-    Ok(Json(FeedResponse {
-        id: feed.id.to_string(),
-        title: feed.title,
-        url: feed.url,
-        last_synced_at: feed.last_synced_at,
-    }))
+    Ok(Json(FeedResponse::from((feed, Vec::from_iter(previous_url)))).into_response())
+}
+
+/// Finds a feed that has already been added with the given url, either as its
+/// current url or one it was redirected from.
+async fn existing_feed_response(app: &App, url: &str) -> Result<Option<Response>, DbErr> {
+    let feed = match Feeds::find()
+        .filter(feeds::Column::Url.eq(url))
+        .one(&app.db)
+        .await?
+    {
+        Some(feed) => Some(feed),
+        None => PreviousFeedUrls::find()
+            .filter(previous_feed_urls::Column::Url.eq(url))
+            .find_also_related(Feeds)
+            .one(&app.db)
+            .await?
+            .and_then(|(_, feed)| feed),
+    };
+
+    let Some(feed) = feed else {
+        return Ok(None);
+    };
+
+    let previous_urls = feed.find_related(PreviousFeedUrls).all(&app.db).await?;
+
+    Ok(Some(
+        Json(FeedResponse::from((feed, previous_urls))).into_response(),
+    ))
+}
+
+/// Fetches a feed that is being added, returning the url it should be added
+/// with, which is where it has moved to if it was permanently redirected.
+async fn fetch_new_feed(
+    app: &App,
+    options: &RequestOptions,
+    url: &str,
+) -> eyre::Result<(String, ParsedFeed)> {
+    let fetch = fetch_feed(
+        &app.clients.get(options)?,
+        &app.host_limiter,
+        &app.fetch_policy,
+        options,
+        url,
+        &CacheHeaders::default(),
+    )
+    .await?;
+
+    let url = fetch.moved_to.map_or_else(|| url.to_owned(), String::from);
+
+    match fetch.outcome {
+        FetchOutcome::Fetched(feed, _) => Ok((url, *feed)),
+        FetchOutcome::NotModified => Err(eyre!("unexpected 304 response for {url}")),
+        FetchOutcome::Gone => Err(eyre!("{url} is gone")),
+    }
+}
+
+/// Looks for feeds belonging to a website, first through the links advertised
+/// by the page and then by trying some commonly used feed paths.
+async fn discover_feeds(
+    app: &App,
+    options: &RequestOptions,
+    page: &HtmlPage,
+) -> Vec<FeedCandidate> {
+    let candidates = discovery::find_feed_links(&page.content, &page.url);
+    if !candidates.is_empty() {
+        return candidates;
+    }
+
+    let probes = discovery::COMMON_FEED_PATHS
+        .iter()
+        .filter_map(|path| page.url.join(path).ok())
+        .map(|url| async move {
+            match fetch_new_feed(app, options, url.as_str()).await {
+                Ok((url, feed)) => Some(FeedCandidate {
+                    title: Some(feed.title),
+                    url,
+                }),
+                Err(e) => {
+                    trace!("no feed found at {url}: {e}");
+                    None
+                }
+            }
+        });
+
+    futures::future::join_all(probes)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
 }
 
 #[derive(Clone, Serialize)]
@@ -406,25 +1049,65 @@ struct PostResponse {
     post_time: String,
     thumbnail: Option<String>,
     description: Option<String>,
+    url: Option<String>,
+    updated_time: Option<String>,
+    author: Option<String>,
+    /// Starred posts are never pruned.
+    starred: bool,
+    /// Only included when fetching a single post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    enclosures: Vec<EnclosureResponse>,
+}
+
+#[derive(Clone, Serialize)]
+struct EnclosureResponse {
     url: String,
+    mime_type: Option<String>,
+    length: Option<i64>,
+    /// Duration in seconds.
+    duration: Option<i64>,
+}
+
+impl From<(posts::Model, Vec<enclosures::Model>)> for PostResponse {
+    fn from((post, enclosures): (posts::Model, Vec<enclosures::Model>)) -> Self {
+        PostResponse {
+            id: post.id.to_string(),
+            feed_id: post.feed_id.to_string(),
+            title: post.title,
+            post_time: post.publish_time,
+            thumbnail: post.thumbnail,
+            description: post.description,
+            url: post.url,
+            updated_time: post.updated_time,
+            author: post.author,
+            starred: post.starred,
+            content: post.content,
+            enclosures: enclosures
+                .into_iter()
+                .map(|enclosure| EnclosureResponse {
+                    url: enclosure.url,
+                    mime_type: enclosure.mime_type,
+                    length: enclosure.length,
+                    duration: enclosure.duration,
+                })
+                .collect(),
+        }
+    }
 }
 
 async fn get_posts(State(app): State<App>) -> Result<impl IntoResponse, ApiError> {
     let posts = Posts::find()
         .order_by_desc(posts::Column::PublishTime)
+        .find_with_related(Enclosures)
         .all(&app.db)
         .await?;
     Ok(Json(
         posts
             .into_iter()
             .map(|post| PostResponse {
-                id: post.id.to_string(),
-                feed_id: post.feed_id.to_string(),
-                title: post.title,
-                post_time: post.publish_time,
-                thumbnail: post.thumbnail,
-                description: post.description,
-                url: post.url,
+                content: None,
+                ..PostResponse::from(post)
             })
             .collect_vec(),
     ))
@@ -438,345 +1121,1294 @@ async fn get_post(
         .one(&app.db)
         .await?
         .ok_or(ApiError::NotFound)?;
-    Ok(Json(PostResponse {
-        id: post.id.to_string(),
-        feed_id: post.feed_id.to_string(),
-        title: post.title,
-        post_time: post.publish_time,
-        thumbnail: post.thumbnail,
-        description: post.description,
-        url: post.url,
-    }))
+    let enclosures = post.find_related(Enclosures).all(&app.db).await?;
+    Ok(Json(PostResponse::from((post, enclosures))))
 }
 
-struct SyncRequest {
-    scope: SyncScope,
-    notify: bool,
+#[derive(Deserialize)]
+struct UpdatePostReq {
+    starred: Option<bool>,
 }
 
-enum SyncScope {
-    All,
-    Feed(Uuid),
-}
+async fn update_post(
+    State(app): State<App>,
+    extract::Path(id): extract::Path<Uuid>,
+    Json(req): Json<UpdatePostReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = Posts::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
 
-async fn run_sync_worker(
-    mut receiver: mpsc::UnboundedReceiver<SyncRequest>,
-    http_client: Client,
-    db: DatabaseConnection,
-    push_client: PushClient,
-) {
-    let worker = SyncWorker {
-        http_client,
-        db,
-        push_client,
-    };
+    let mut post = post.into_active_model();
 
-    while let Some(req) = receiver.recv().await {
-        if let Err(e) = worker.process_request(req).await {
-            error!("{e:?}");
-        }
+    if let Some(starred) = req.starred {
+        post.starred = ActiveValue::Set(starred);
     }
-}
-
-struct SyncWorker {
-    http_client: Client,
-    db: DatabaseConnection,
-    push_client: PushClient,
-}
 
-impl SyncWorker {
-    async fn process_request(&self, req: SyncRequest) -> eyre::Result<()> {
-        let feeds = match req.scope {
-            SyncScope::All => Feeds::find().all(&self.db).await?,
-            SyncScope::Feed(id) => Feeds::find_by_id(id)
-                .one(&self.db)
-                .await?
-                .into_iter()
-                .collect_vec(),
-        };
+    let post = post.update(&app.db).await?;
+    let enclosures = post.find_related(Enclosures).all(&app.db).await?;
 
-        for feed_model in feeds {
-            tracing::info!("syncing posts from {}", feed_model.url);
+    Ok(Json(PostResponse {
+        content: None,
+        ..PostResponse::from((post, enclosures))
+    }))
+}
 
-            let feed = match fetch_feed(&self.http_client, &feed_model.url).await {
-                Ok(feed) => feed,
-                Err(e) => {
-                    error!("{e:?}");
-                    continue;
-                }
-            };
+#[derive(Clone, Serialize)]
+struct PostRevisionResponse {
+    title: String,
+    description: Option<String>,
+    content: Option<String>,
+    thumbnail: Option<String>,
+    updated_time: Option<String>,
+    revised_at: i64,
+}
 
-            match feed {
-                Feed::Atom(feed) => {
-                    for entry in feed.entries {
-                        let description =
-                            entry.summary().map(|v| v.value.as_str()).map(|summary| {
-                                let html = Html::parse_fragment(summary);
-                                html.root_element().text().join("")
-                            });
-
-                        let content_url = entry
-                            .links
-                            .iter()
-                            .find(|link| {
-                                link.rel == "alternate"
-                                    && link.mime_type.as_deref() == Some("text/html")
-                            })
-                            .or_else(|| entry.links.iter().find(|link| link.rel == "alternate"))
-                            .or_else(|| entry.links.first())
-                            .map(|link| &link.href)
-                            .unwrap_or(&entry.id);
-
-                        let post_id = Uuid::new_v4();
-                        let post = posts::ActiveModel {
-                            id: ActiveValue::Set(post_id),
-                            feed_id: ActiveValue::Set(feed_model.id),
-                            url: ActiveValue::Set(content_url.to_owned()),
-                            title: ActiveValue::Set(entry.title.value),
-                            description: ActiveValue::Set(description),
-                            content: ActiveValue::Set(
-                                entry.content.and_then(|content| content.value),
-                            ),
-                            publish_time: ActiveValue::Set(
-                                entry
-                                    .published
-                                    .map(|t| t.to_rfc3339())
-                                    .unwrap_or_else(|| entry.updated.to_rfc3339()),
-                            ),
-                            thumbnail: ActiveValue::Set(None),
-                        };
-
-                        trace!(?post.title, ?post.url, "inserting post");
-
-                        let post = match post.insert(&self.db).await {
-                            Ok(post) => post,
-                            Err(e) => {
-                                if let Some(SqlErr::UniqueConstraintViolation(_)) = e.sql_err() {
-                                    trace!("skipping post as it already exists");
-                                } else {
-                                    error!("{e}");
-                                }
-                                continue;
-                            }
-                        };
-
-                        let content = (|| fetch_page_content(&self.http_client, &post.url))
-                            .retry(ExponentialBuilder::default())
-                            .sleep(tokio::time::sleep)
-                            .notify(|err, duration| {
-                                tracing::warn!("retrying {err:?} after {duration:?}");
-                            })
-                            .await?;
-
-                        let image = {
-                            Html::parse_document(&content)
-                                .select(&Selector::parse("meta[property=\"og:image\"]").unwrap())
-                                .next()
-                                .and_then(|el| el.attr("content"))
-                                .map(ToOwned::to_owned)
-                        };
-
-                        posts::ActiveModel {
-                            id: ActiveValue::Unchanged(post_id),
-                            thumbnail: ActiveValue::Set(image),
-                            ..Default::default()
-                        }
-                        .update(&self.db)
-                        .await?;
-
-                        if req.notify {
-                            for subscription in PushSubscriptions::find().all(&self.db).await? {
-                                match self
-                                    .push_client
-                                    .send_message(
-                                        &subscription,
-                                        &json!({
-                                            "id": post.id.to_string(),
-                                            "title": post.title,
-                                        }),
-                                    )
-                                    .await
-                                {
-                                    Ok(is_valid) => {
-                                        if !is_valid {
-                                            PushSubscriptions::delete_by_id(subscription.id)
-                                                .exec(&self.db)
-                                                .await?;
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!(
-                                            subscription.id,
-                                            subscription.endpoint,
-                                            "Failed to send push message: {e}",
-                                        );
-                                    }
-                                }
-                            }
+async fn get_post_revisions(
+    State(app): State<App>,
+    extract::Path(id): extract::Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = Posts::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let revisions = post
+        .find_related(PostRevisions)
+        .order_by_desc(post_revisions::Column::RevisedAt)
+        .all(&app.db)
+        .await?;
+
+    Ok(Json(
+        revisions
+            .into_iter()
+            .map(|revision| PostRevisionResponse {
+                title: revision.title,
+                description: revision.description,
+                content: revision.content,
+                thumbnail: revision.thumbnail,
+                updated_time: revision.updated_time,
+                revised_at: revision.revised_at,
+            })
+            .collect_vec(),
+    ))
+}
+
+#[derive(Clone, Serialize)]
+struct PlaybackResponse {
+    post_id: String,
+    /// Position in seconds.
+    position: i64,
+    completed: bool,
+    updated_at: i64,
+}
+
+impl From<playback_progress::Model> for PlaybackResponse {
+    fn from(progress: playback_progress::Model) -> Self {
+        PlaybackResponse {
+            post_id: progress.post_id.to_string(),
+            position: progress.position,
+            completed: progress.completed,
+            updated_at: progress.updated_at,
+        }
+    }
+}
+
+async fn get_playback(
+    State(app): State<App>,
+    extract::Path(id): extract::Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let progress = PlaybackProgress::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    Ok(Json(PlaybackResponse::from(progress)))
+}
+
+#[derive(Deserialize)]
+struct UpdatePlaybackReq {
+    /// Position in seconds.
+    position: i64,
+    #[serde(default)]
+    completed: bool,
+}
+
+async fn update_playback(
+    State(app): State<App>,
+    extract::Path(id): extract::Path<Uuid>,
+    Json(req): Json<UpdatePlaybackReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    if req.position < 0 {
+        return Err(ApiError::BadRequest("position must not be negative"));
+    }
+
+    Posts::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let progress = playback_progress::ActiveModel {
+        post_id: ActiveValue::Set(id),
+        position: ActiveValue::Set(req.position),
+        completed: ActiveValue::Set(req.completed),
+        updated_at: ActiveValue::Set(Utc::now().timestamp()),
+    };
+
+    let progress = PlaybackProgress::insert(progress)
+        .on_conflict(
+            OnConflict::column(playback_progress::Column::PostId)
+                .update_columns([
+                    playback_progress::Column::Position,
+                    playback_progress::Column::Completed,
+                    playback_progress::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(&app.db)
+        .await?;
+
+    Ok(Json(PlaybackResponse::from(progress)))
+}
+
+#[derive(Clone, Serialize)]
+struct ContinueListeningResponse {
+    post: PostResponse,
+    playback: PlaybackResponse,
+}
+
+/// Posts that have been started but not finished, most recently played first.
+async fn get_continue_listening(State(app): State<App>) -> Result<impl IntoResponse, ApiError> {
+    let (progress, posts): (Vec<_>, Vec<_>) = PlaybackProgress::find()
+        .filter(playback_progress::Column::Completed.eq(false))
+        .filter(playback_progress::Column::Position.gt(0))
+        .order_by_desc(playback_progress::Column::UpdatedAt)
+        .find_also_related(Posts)
+        .all(&app.db)
+        .await?
+        .into_iter()
+        .filter_map(|(progress, post)| Some((progress, post?)))
+        .unzip();
+
+    let enclosures = posts.load_many(Enclosures, &app.db).await?;
+
+    Ok(Json(
+        progress
+            .into_iter()
+            .zip(posts.into_iter().zip(enclosures))
+            .map(|(progress, post)| ContinueListeningResponse {
+                post: PostResponse {
+                    content: None,
+                    ..PostResponse::from(post)
+                },
+                playback: PlaybackResponse::from(progress),
+            })
+            .collect_vec(),
+    ))
+}
+
+#[derive(Serialize)]
+struct SyncJobResponse {
+    id: String,
+    status: SyncJobStatus,
+    new_posts: u64,
+    error: Option<String>,
+    created_at: i64,
+    finished_at: Option<i64>,
+}
+
+impl From<SyncJob> for SyncJobResponse {
+    fn from(job: SyncJob) -> Self {
+        SyncJobResponse {
+            id: job.id.to_string(),
+            status: job.status,
+            new_posts: job.new_posts,
+            error: job.error,
+            created_at: job.created_at.timestamp(),
+            finished_at: job.finished_at.map(|t| t.timestamp()),
+        }
+    }
+}
+
+async fn trigger_feed_sync(
+    extract::Path(id): extract::Path<Uuid>,
+    State(app): State<App>,
+) -> Result<impl IntoResponse, ApiError> {
+    let feed = Feeds::find_by_id(id)
+        .one(&app.db)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    enqueue_sync_job(&app, SyncScope::Feed(feed.id))
+}
+
+async fn trigger_sync(State(app): State<App>) -> Result<impl IntoResponse, ApiError> {
+    enqueue_sync_job(&app, SyncScope::All)
+}
+
+fn enqueue_sync_job(
+    app: &App,
+    scope: SyncScope,
+) -> Result<(StatusCode, Json<SyncJobResponse>), ApiError> {
+    let job = app.sync_jobs.create();
+
+    app.sync_sender
+        .send(SyncRequest {
+            scope,
+            notify: true,
+            job: Some(job.id),
+        })
+        .map_err(|e| eyre!("failed to enqueue sync: {e}"))?;
+
+    Ok((StatusCode::ACCEPTED, Json(SyncJobResponse::from(job))))
+}
+
+async fn get_sync_job(
+    extract::Path(id): extract::Path<Uuid>,
+    State(app): State<App>,
+) -> Result<impl IntoResponse, ApiError> {
+    let job = app.sync_jobs.get(id).ok_or(ApiError::NotFound)?;
+    Ok(Json(SyncJobResponse::from(job)))
+}
+
+/// Streams [`Event`]s to the client as server-sent events.
+async fn get_events(
+    State(app): State<App>,
+) -> Sse<impl Stream<Item = Result<sse::Event, axum::Error>>> {
+    let events = app
+        .events
+        .subscribe()
+        .map(|event| sse::Event::default().event(event.name()).json_data(&event));
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+struct SyncRequest {
+    scope: SyncScope,
+    notify: bool,
+    /// The job to report progress to, for syncs requested through the API.
+    job: Option<Uuid>,
+}
+
+enum SyncScope {
+    All,
+    /// Feeds whose scheduled sync time has passed.
+    Due,
+    Feed(Uuid),
+}
+
+async fn run_sync_worker(mut receiver: mpsc::UnboundedReceiver<SyncRequest>, worker: SyncWorker) {
+    while let Some(req) = receiver.recv().await {
+        if let Err(e) = worker.process_request(req).await {
+            error!("{e:?}");
+        }
+    }
+}
+
+struct SyncWorker {
+    clients: Arc<ClientPool>,
+    host_limiter: Arc<HostLimiter>,
+    fetch_policy: Arc<FetchPolicy>,
+    db: DatabaseConnection,
+    push_client: PushClient,
+    sync_jobs: Arc<SyncJobs>,
+    events: Arc<Events>,
+    /// Maximum number of feeds to sync concurrently.
+    concurrency: usize,
+}
+
+impl SyncWorker {
+    async fn process_request(&self, req: SyncRequest) -> eyre::Result<()> {
+        let Some(job) = req.job else {
+            self.sync_feeds(req.scope, req.notify, None).await?;
+            return Ok(());
+        };
+
+        self.sync_jobs.start(job);
+
+        // Syncing several feeds only fails if none of them could be synced,
+        // so that one broken feed doesn't fail every refresh.
+        let error = match self.sync_feeds(req.scope, req.notify, Some(job)).await {
+            Ok(results) if !results.is_empty() && results.iter().all(Result::is_err) => results
+                .into_iter()
+                .find_map(Result::err)
+                .map(|e| format!("{e:#}")),
+            Ok(_) => None,
+            Err(e) => Some(format!("{e:#}")),
+        };
+
+        self.sync_jobs.finish(job, error);
+
+        Ok(())
+    }
+
+    /// Syncs the feeds in scope, returning the number of new posts or the
+    /// error for each of them.
+    async fn sync_feeds(
+        &self,
+        scope: SyncScope,
+        notify: bool,
+        job: Option<Uuid>,
+    ) -> eyre::Result<Vec<eyre::Result<u64>>> {
+        let feeds = match scope {
+            SyncScope::All => {
+                Feeds::find()
+                    .filter(feeds::Column::Active.eq(true))
+                    .all(&self.db)
+                    .await?
+            }
+            SyncScope::Due => {
+                Feeds::find()
+                    .filter(feeds::Column::Active.eq(true))
+                    .filter(
+                        Condition::any()
+                            .add(feeds::Column::NextSyncAt.is_null())
+                            .add(feeds::Column::NextSyncAt.lte(Utc::now().timestamp())),
+                    )
+                    .all(&self.db)
+                    .await?
+            }
+            SyncScope::Feed(id) => Feeds::find_by_id(id)
+                .one(&self.db)
+                .await?
+                .into_iter()
+                .collect_vec(),
+        };
+
+        let results = stream::iter(feeds)
+            .map(|feed| async move {
+                let url = feed.url.clone();
+                let feed_id = feed.id.to_string();
+                let res = self.sync_feed(feed, notify).await;
+
+                match &res {
+                    Ok(new_posts) => {
+                        if let Some(job) = job {
+                            self.sync_jobs.add_new_posts(job, *new_posts);
                         }
+
+                        self.events.publish(Event::FeedSynced {
+                            feed_id,
+                            new_posts: *new_posts,
+                        });
                     }
-                }
-                Feed::Rss(channel) => {
-                    for item in channel.items {
-                        let Some(content_url) = item.link else {
-                            error!("RSS post without link: {:?}", item);
-                            continue;
-                        };
-
-                        let description = item.description.as_deref().map(|summary| {
-                            let html = Html::parse_fragment(summary);
-                            html.root_element().text().join("")
+                    Err(e) => {
+                        error!(url, "failed to sync feed: {e:?}");
+                        self.events.publish(Event::FeedError {
+                            feed_id,
+                            error: format!("{e:#}"),
                         });
+                    }
+                }
 
-                        let post_id = Uuid::new_v4();
-                        let post = posts::ActiveModel {
-                            id: ActiveValue::Set(post_id),
-                            feed_id: ActiveValue::Set(feed_model.id),
-                            url: ActiveValue::Set(content_url),
-                            title: ActiveValue::Set(
-                                item.title.unwrap_or_else(|| "Untitled".to_owned()),
-                            ),
-                            description: ActiveValue::Set(description),
-                            content: ActiveValue::Set(None),
-                            publish_time: ActiveValue::Set(
-                                item.pub_date
-                                    .and_then(|t| {
-                                        DateTime::parse_from_rfc2822(&t)
-                                            .ok()
-                                            .map(|t| t.to_rfc3339())
-                                    })
-                                    .unwrap_or_else(|| Local::now().to_rfc3339()),
-                            ),
-                            thumbnail: ActiveValue::Set(None),
-                        };
-
-                        trace!(?post.title, ?post.url, "inserting post");
-
-                        let post = match post.insert(&self.db).await {
-                            Ok(post) => post,
-                            Err(e) => {
-                                if let Some(SqlErr::UniqueConstraintViolation(_)) = e.sql_err() {
-                                    trace!("skipping post as it already exists");
-                                } else {
-                                    error!("{e}");
-                                }
-                                continue;
-                            }
-                        };
-
-                        let content = (|| fetch_page_content(&self.http_client, &post.url))
-                            .retry(ExponentialBuilder::default())
-                            .sleep(tokio::time::sleep)
-                            .notify(|err, duration| {
-                                tracing::warn!("retrying {err:?} after {duration:?}");
-                            })
-                            .await?;
-
-                        let image = {
-                            Html::parse_document(&content)
-                                .select(&Selector::parse("meta[property=\"og:image\"]").unwrap())
-                                .next()
-                                .and_then(|el| el.attr("content"))
-                                .map(ToOwned::to_owned)
-                        };
-
-                        posts::ActiveModel {
-                            id: ActiveValue::Unchanged(post_id),
-                            thumbnail: ActiveValue::Set(image),
-                            ..Default::default()
-                        }
-                        .update(&self.db)
-                        .await?;
-
-                        if req.notify {
-                            for subscription in PushSubscriptions::find().all(&self.db).await? {
-                                match self
-                                    .push_client
-                                    .send_message(
-                                        &subscription,
-                                        &json!({
-                                            "id": post.id.to_string(),
-                                            "title": post.title,
-                                        }),
-                                    )
-                                    .await
-                                {
-                                    Ok(is_valid) => {
-                                        if !is_valid {
-                                            PushSubscriptions::delete_by_id(subscription.id)
-                                                .exec(&self.db)
-                                                .await?;
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!(
-                                            subscription.id,
-                                            subscription.endpoint,
-                                            "Failed to send push message: {e}",
-                                        );
-                                    }
-                                }
-                            }
+                res
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+
+        Ok(results)
+    }
+
+    /// Syncs a single feed, returning the number of new posts.
+    async fn sync_feed(&self, feed_model: feeds::Model, notify: bool) -> eyre::Result<u64> {
+        tracing::info!("syncing posts from {}", feed_model.url);
+
+        let cache_headers = CacheHeaders {
+            etag: feed_model.etag.clone(),
+            last_modified: feed_model.last_modified.clone(),
+        };
+
+        let options = RequestOptions::from_feed(&feed_model);
+        let clients = self.clients.get(&options)?;
+
+        let fetch = match fetch_feed(
+            &clients,
+            &self.host_limiter,
+            &self.fetch_policy,
+            &options,
+            &feed_model.url,
+            &cache_headers,
+        )
+        .await
+        {
+            Ok(fetch) => fetch,
+            Err(e) => {
+                self.record_failure(feed_model, &e).await?;
+                return Err(e);
+            }
+        };
+
+        let moved_to = self.record_move(&feed_model, fetch.moved_to).await?;
+
+        let (feed, cache_headers) = match fetch.outcome {
+            FetchOutcome::Fetched(feed, cache_headers) => (*feed, cache_headers),
+            FetchOutcome::NotModified => {
+                trace!("feed not modified since last sync");
+                let now = Utc::now();
+                let hints = FeedHints::from_feed(&feed_model);
+                let interval = self.stored_sync_interval(&feed_model, &hints).await?;
+                let mut active_feed = feed_model.into_active_model();
+                if let Some(url) = moved_to {
+                    active_feed.url = ActiveValue::Set(url);
+                }
+                active_feed.active = ActiveValue::Set(true);
+                active_feed.last_synced_at = ActiveValue::Set(Some(now.timestamp()));
+                active_feed.last_success_at = ActiveValue::Set(Some(now.timestamp()));
+                active_feed.consecutive_failures = ActiveValue::Set(0);
+                active_feed.next_sync_at = ActiveValue::Set(Some(
+                    schedule::next_sync_at(now, interval, &hints).timestamp(),
+                ));
+                active_feed.update(&self.db).await?;
+                return Ok(0);
+            }
+            FetchOutcome::Gone => {
+                tracing::warn!(feed_model.url, "feed is gone, deactivating it");
+
+                let e = eyre!("{} is gone", feed_model.url);
+                let mut active_feed = feed_model.into_active_model();
+                active_feed.active = ActiveValue::Set(false);
+                active_feed.last_error = ActiveValue::Set(Some(format!("{e:#}")));
+                active_feed.last_error_at = ActiveValue::Set(Some(Utc::now().timestamp()));
+                active_feed.next_sync_at = ActiveValue::Set(None);
+                active_feed.update(&self.db).await?;
+
+                return Err(e);
+            }
+        };
+
+        let feed_url = Url::parse(moved_to.as_deref().unwrap_or(&feed_model.url))?;
+        // Posts without a usable date are dated by when they were first seen.
+        let first_seen = Utc::now();
+
+        let images = resolve_feed_images(
+            &clients,
+            &self.host_limiter,
+            &self.fetch_policy,
+            &options,
+            &feed_url,
+            &feed,
+            FeedImages {
+                icon: feed_model.icon.clone(),
+                thumbnail: feed_model.thumbnail.clone(),
+                site_checked_at: feed_model.site_images_checked_at,
+            },
+        )
+        .await;
+
+        let post_times = feed.post_times();
+        let ParsedFeed {
+            is_podcast,
+            hints,
+            entries,
+            ..
+        } = feed;
+
+        let mut new_posts = 0;
+
+        for entry in entries {
+            if self
+                .insert_post(&feed_model, entry, first_seen, notify)
+                .await?
+            {
+                new_posts += 1;
+            }
+        }
+
+        let now = Utc::now();
+        let interval = schedule::sync_interval(
+            feed_model.sync_interval.map(schedule::user_interval),
+            &hints,
+            now,
+            post_times,
+        );
+
+        trace!(?interval, "scheduling next sync");
+
+        let mut active_feed = feed_model.into_active_model();
+        if let Some(url) = moved_to {
+            active_feed.url = ActiveValue::Set(url);
+        }
+        active_feed.active = ActiveValue::Set(true);
+        active_feed.last_synced_at = ActiveValue::Set(Some(now.timestamp()));
+        active_feed.last_success_at = ActiveValue::Set(Some(now.timestamp()));
+        active_feed.consecutive_failures = ActiveValue::Set(0);
+        active_feed.next_sync_at = ActiveValue::Set(Some(
+            schedule::next_sync_at(now, interval, &hints).timestamp(),
+        ));
+        active_feed.is_podcast = ActiveValue::Set(is_podcast);
+        hints.store(&mut active_feed);
+        active_feed.icon = ActiveValue::Set(images.icon);
+        active_feed.thumbnail = ActiveValue::Set(images.thumbnail);
+        active_feed.site_images_checked_at = ActiveValue::Set(images.site_checked_at);
+        active_feed.etag = ActiveValue::Set(cache_headers.etag);
+        active_feed.last_modified = ActiveValue::Set(cache_headers.last_modified);
+        active_feed.update(&self.db).await?;
+
+        Ok(new_posts)
+    }
+
+    /// Remembers the current url of a feed that has permanently moved, so that
+    /// it isn't added again under its old url. Returns the url the feed
+    /// should be updated to.
+    async fn record_move(
+        &self,
+        feed: &feeds::Model,
+        moved_to: Option<Url>,
+    ) -> eyre::Result<Option<String>> {
+        let Some(url) = moved_to.map(String::from).filter(|url| *url != feed.url) else {
+            return Ok(None);
+        };
+
+        let taken = Feeds::find()
+            .filter(feeds::Column::Url.eq(&url))
+            .one(&self.db)
+            .await?
+            .is_some();
+
+        if taken {
+            tracing::warn!(feed.url, url, "feed moved to the url of another feed");
+            return Ok(None);
+        }
+
+        tracing::info!(feed.url, url, "feed has permanently moved");
+
+        PreviousFeedUrls::insert(previous_feed_urls::ActiveModel {
+            id: ActiveValue::NotSet,
+            feed_id: ActiveValue::Set(feed.id),
+            url: ActiveValue::Set(feed.url.clone()),
+            moved_at: ActiveValue::Set(Utc::now().timestamp()),
+        })
+        .on_conflict(
+            OnConflict::column(previous_feed_urls::Column::Url)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await?;
+
+        Ok(Some(url))
+    }
+
+    /// Records a failed sync and schedules a retry, backing off exponentially
+    /// while the feed keeps failing.
+    async fn record_failure(&self, feed_model: feeds::Model, e: &eyre::Report) -> eyre::Result<()> {
+        let now = Utc::now();
+        let failures = feed_model.consecutive_failures + 1;
+
+        if failures == schedule::BROKEN_AFTER_FAILURES {
+            tracing::warn!(feed_model.url, "feed has failed {failures} times in a row");
+        }
+
+        let hints = FeedHints::from_feed(&feed_model);
+        let interval = schedule::failure_backoff(
+            self.stored_sync_interval(&feed_model, &hints).await?,
+            failures,
+        );
+
+        let mut next_sync_at = schedule::next_sync_at(now, interval, &hints);
+        if let Some(RateLimited { until, .. }) = e.downcast_ref() {
+            next_sync_at = next_sync_at.max(*until);
+        }
+
+        let mut active_feed = feed_model.into_active_model();
+        active_feed.last_error = ActiveValue::Set(Some(format!("{e:#}")));
+        active_feed.last_error_at = ActiveValue::Set(Some(now.timestamp()));
+        active_feed.consecutive_failures = ActiveValue::Set(failures);
+        active_feed.next_sync_at = ActiveValue::Set(Some(next_sync_at.timestamp()));
+        active_feed.update(&self.db).await?;
+
+        Ok(())
+    }
+
+    /// Computes a feed's sync interval from the posts already stored for it,
+    /// for when there is no new feed content to base a decision on.
+    async fn stored_sync_interval(
+        &self,
+        feed: &feeds::Model,
+        hints: &FeedHints,
+    ) -> eyre::Result<TimeDelta> {
+        let post_times = Posts::find()
+            .filter(posts::Column::FeedId.eq(feed.id))
+            .order_by_desc(posts::Column::PublishTime)
+            .limit(schedule::FREQUENCY_SAMPLE_SIZE as u64)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .filter_map(|post| DateTime::parse_from_rfc3339(&post.publish_time).ok())
+            .map(|t| t.to_utc())
+            .collect();
+
+        Ok(schedule::sync_interval(
+            feed.sync_interval.map(schedule::user_interval),
+            hints,
+            Utc::now(),
+            post_times,
+        ))
+    }
+
+    /// Updates an existing post if the feed's copy of it has changed, keeping
+    /// the previous version as a revision.
+    async fn update_post(
+        &self,
+        feed: &feeds::Model,
+        existing: posts::Model,
+        entry: FeedEntry,
+    ) -> eyre::Result<()> {
+        self.update_enclosures(&existing, entry.enclosures).await?;
+
+        let title = entry.title;
+        let description = entry.description;
+        // Extracted articles replace whatever content the feed has, unless
+        // extraction failed and there is nothing to replace.
+        let content = if feed.fetch_full_content {
+            existing.content.clone().or(entry.content)
+        } else {
+            entry.content
+        };
+        // Thumbnails scraped from the article page aren't in the feed, so
+        // only replace the existing one if the feed provides its own.
+        let thumbnail = entry.thumbnail.or_else(|| existing.thumbnail.clone());
+        let updated_time = entry.updated.map(|t| t.to_rfc3339());
+        let author = entry.author;
+
+        // A new thumbnail alone isn't a new revision of the post, so it is
+        // just updated in place.
+        let changed = title != existing.title
+            || description != existing.description
+            || content != existing.content;
+
+        if !changed && thumbnail == existing.thumbnail && updated_time == existing.updated_time {
+            trace!(existing.guid, "skipping post as it already exists");
+            return Ok(());
+        }
+
+        let txn = self.db.begin().await?;
+
+        if changed {
+            trace!(existing.guid, "post has changed, saving revision");
+
+            post_revisions::ActiveModel {
+                id: ActiveValue::NotSet,
+                post_id: ActiveValue::Set(existing.id),
+                title: ActiveValue::Set(existing.title.clone()),
+                description: ActiveValue::Set(existing.description.clone()),
+                content: ActiveValue::Set(existing.content.clone()),
+                thumbnail: ActiveValue::Set(existing.thumbnail.clone()),
+                updated_time: ActiveValue::Set(existing.updated_time.clone()),
+                revised_at: ActiveValue::Set(Utc::now().timestamp()),
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let mut active_post = existing.into_active_model();
+        active_post.title = ActiveValue::Set(title);
+        active_post.description = ActiveValue::Set(description);
+        active_post.content = ActiveValue::Set(content);
+        active_post.thumbnail = ActiveValue::Set(thumbnail);
+        active_post.updated_time = ActiveValue::Set(updated_time);
+        active_post.author = ActiveValue::Set(author);
+        active_post.update(&txn).await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Replaces the stored enclosures of a post if the feed's have changed.
+    async fn update_enclosures(
+        &self,
+        post: &posts::Model,
+        enclosures: Vec<podcast::Enclosure>,
+    ) -> eyre::Result<()> {
+        let existing = post
+            .find_related(Enclosures)
+            .order_by_asc(enclosures::Column::Id)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|enclosure| podcast::Enclosure {
+                url: enclosure.url,
+                mime_type: enclosure.mime_type,
+                length: enclosure.length,
+                duration: enclosure.duration,
+            })
+            .collect_vec();
+
+        if existing == enclosures {
+            return Ok(());
+        }
+
+        trace!(post.guid, "updating enclosures");
+
+        let txn = self.db.begin().await?;
+
+        Enclosures::delete_many()
+            .filter(enclosures::Column::PostId.eq(post.id))
+            .exec(&txn)
+            .await?;
+
+        for enclosure in enclosures {
+            enclosures::ActiveModel {
+                id: ActiveValue::NotSet,
+                post_id: ActiveValue::Set(post.id),
+                url: ActiveValue::Set(enclosure.url),
+                mime_type: ActiveValue::Set(enclosure.mime_type),
+                length: ActiveValue::Set(enclosure.length),
+                duration: ActiveValue::Set(enclosure.duration),
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Inserts a newly discovered post, looks up a thumbnail and the full
+    /// article for it if needed, and notifies push subscribers.
+    ///
+    /// Entries without a date are dated by when they were `first_seen`.
+    ///
+    /// Returns whether the post was new, rather than an existing one.
+    async fn insert_post(
+        &self,
+        feed: &feeds::Model,
+        entry: FeedEntry,
+        first_seen: DateTime<Utc>,
+        notify: bool,
+    ) -> eyre::Result<bool> {
+        let existing = Posts::find()
+            .filter(posts::Column::FeedId.eq(feed.id))
+            .filter(posts::Column::Guid.eq(&entry.guid))
+            .one(&self.db)
+            .await?;
+
+        if let Some(existing) = existing {
+            self.update_post(feed, existing, entry).await?;
+            return Ok(false);
+        }
+
+        // Pruned posts are usually still in the feed, and would otherwise come
+        // back as new ones on every sync.
+        let tombstone = PostTombstones::find()
+            .filter(post_tombstones::Column::FeedId.eq(feed.id))
+            .filter(
+                Condition::any()
+                    .add(post_tombstones::Column::Guid.eq(&entry.guid))
+                    .add_option(
+                        entry
+                            .url
+                            .as_ref()
+                            .map(|url| post_tombstones::Column::Url.eq(url)),
+                    ),
+            )
+            .one(&self.db)
+            .await?;
+
+        if tombstone.is_some() {
+            trace!(entry.guid, "skipping pruned post");
+            return Ok(false);
+        }
+
+        // Posts synced before guids were tracked have their url as their guid.
+        if let Some(url) = &entry.url
+            && *url != entry.guid
+        {
+            let res = Posts::update_many()
+                .col_expr(posts::Column::Guid, Expr::value(&entry.guid))
+                .filter(posts::Column::FeedId.eq(feed.id))
+                .filter(posts::Column::Guid.eq(url))
+                .exec(&self.db)
+                .await?;
+
+            if res.rows_affected > 0 {
+                trace!(entry.guid, "updated guid of existing post");
+                return Ok(false);
+            }
+        }
+
+        trace!(entry.title, ?entry.url, "inserting post");
+
+        let publish_time = entry.published.unwrap_or_else(|| first_seen.fixed_offset());
+
+        let post = posts::ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            feed_id: ActiveValue::Set(feed.id),
+            guid: ActiveValue::Set(entry.guid),
+            url: ActiveValue::Set(entry.url),
+            title: ActiveValue::Set(entry.title),
+            description: ActiveValue::Set(entry.description),
+            content: ActiveValue::Set(entry.content),
+            // Stored in UTC so that posts sort chronologically by the text.
+            publish_time: ActiveValue::Set(publish_time.to_utc().to_rfc3339()),
+            thumbnail: ActiveValue::Set(entry.thumbnail),
+            updated_time: ActiveValue::Set(entry.updated.map(|t| t.to_rfc3339())),
+            author: ActiveValue::Set(entry.author),
+            first_seen_at: ActiveValue::Set(Some(first_seen.timestamp())),
+            starred: ActiveValue::NotSet,
+        };
+
+        let enclosures = entry.enclosures;
+
+        let post = match post.insert(&self.db).await {
+            Ok(post) => post,
+            Err(e) => {
+                if let Some(SqlErr::UniqueConstraintViolation(_)) = e.sql_err() {
+                    trace!("skipping post as it already exists");
+                } else {
+                    error!("{e}");
+                }
+                return Ok(false);
+            }
+        };
+
+        self.update_enclosures(&post, enclosures).await?;
+
+        if let Some(url) = &post.url
+            && (post.thumbnail.is_none() || feed.fetch_full_content)
+        {
+            let options = RequestOptions::from_feed(feed);
+            let clients = self.clients.get(&options)?;
+            let content = (|| {
+                fetch_page_content(
+                    &clients,
+                    &self.host_limiter,
+                    &self.fetch_policy,
+                    &options,
+                    url,
+                )
+            })
+            .retry(ExponentialBuilder::default())
+            .sleep(tokio::time::sleep)
+            .when(|err| {
+                !err.is::<RateLimited>() && !err.is::<BodyTooLarge>() && !Blocked::caused(err)
+            })
+            .notify(|err, duration| {
+                tracing::warn!("retrying {err:?} after {duration:?}");
+            })
+            .await;
+
+            match content {
+                Ok(content) => {
+                    let page = scrape_post_page(&content, url, feed.fetch_full_content);
+
+                    let mut active_post = posts::ActiveModel {
+                        id: ActiveValue::Unchanged(post.id),
+                        ..Default::default()
+                    };
+
+                    if post.thumbnail.is_none() {
+                        active_post.thumbnail = ActiveValue::Set(page.image);
+                    }
+
+                    if let Some(article) = page.article {
+                        active_post.content = ActiveValue::Set(Some(article));
+                    }
+
+                    if active_post.is_changed() {
+                        active_post.update(&self.db).await?;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(url, "failed to fetch page content: {e:?}");
+                }
+            }
+        }
+
+        self.events.publish(Event::PostCreated {
+            id: post.id.to_string(),
+            feed_id: post.feed_id.to_string(),
+            title: post.title.clone(),
+            url: post.url.clone(),
+            post_time: post.publish_time.clone(),
+        });
+
+        if notify {
+            for subscription in PushSubscriptions::find().all(&self.db).await? {
+                match self
+                    .push_client
+                    .send_message(
+                        &subscription,
+                        &json!({
+                            "id": post.id.to_string(),
+                            "title": post.title,
+                        }),
+                    )
+                    .await
+                {
+                    Ok(is_valid) => {
+                        if !is_valid {
+                            PushSubscriptions::delete_by_id(subscription.id)
+                                .exec(&self.db)
+                                .await?;
                         }
                     }
+                    Err(e) => {
+                        error!(
+                            subscription.id,
+                            subscription.endpoint, "Failed to send push message: {e}",
+                        );
+                    }
                 }
             }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Details scraped from the web page of a post.
+struct PostPage {
+    image: Option<String>,
+    article: Option<String>,
+}
+
+fn scrape_post_page(html: &str, url: &str, extract_article: bool) -> PostPage {
+    let document = Html::parse_document(html);
+
+    let Ok(url) = Url::parse(url) else {
+        return PostPage {
+            image: None,
+            article: None,
+        };
+    };
 
-            let mut active_feed = feed_model.into_active_model();
-            active_feed.last_synced_at = ActiveValue::Set(Some(Local::now().timestamp()));
-            active_feed.update(&self.db).await?;
+    PostPage {
+        image: thumbnails::from_page(&document, &url),
+        article: extract_article
+            .then(|| readability::extract_article(&document, &url))
+            .flatten(),
+    }
+}
+
+async fn fetch_page_content(
+    clients: &FeedClients,
+    host_limiter: &HostLimiter,
+    policy: &FetchPolicy,
+    options: &RequestOptions,
+    url: &str,
+) -> eyre::Result<String> {
+    let url = Url::parse(url)?;
+    let (res, _permit) =
+        send_page_request(clients, host_limiter, policy, options, Method::GET, url).await?;
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(ToOwned::to_owned);
+    let content = policy.read_body(res).await?;
+    Ok(charset::decode_html(&content, content_type.as_deref()))
+}
+
+/// How long to wait before looking for images on a feed's website again,
+/// when it didn't have them last time.
+const SITE_IMAGES_RECHECK_INTERVAL: TimeDelta = TimeDelta::weeks(1);
+
+#[derive(Debug, Default)]
+struct FeedImages {
+    icon: Option<String>,
+    thumbnail: Option<String>,
+    /// When the website was last scraped for images.
+    site_checked_at: Option<i64>,
+}
+
+/// Determines a feed's icon and cover image, preferring the ones declared by
+/// the feed and falling back to the images of its website.
+///
+/// Scraping the website costs extra requests, so it is only done for images
+/// that aren't already known from a previous sync, and at most once every
+/// [`SITE_IMAGES_RECHECK_INTERVAL`] for websites that don't have them.
+async fn resolve_feed_images(
+    clients: &FeedClients,
+    host_limiter: &HostLimiter,
+    policy: &FetchPolicy,
+    options: &RequestOptions,
+    feed_url: &Url,
+    feed: &ParsedFeed,
+    current: FeedImages,
+) -> FeedImages {
+    let resolve = |url: &str| feed_url.join(url.trim()).ok();
+
+    let mut images = FeedImages {
+        icon: feed
+            .icon
+            .as_deref()
+            .and_then(resolve)
+            .map(String::from)
+            .or(current.icon),
+        thumbnail: feed
+            .logo
+            .as_deref()
+            .and_then(resolve)
+            .map(String::from)
+            .or(current.thumbnail),
+        site_checked_at: current.site_checked_at,
+    };
+
+    if images.icon.is_some() && images.thumbnail.is_some() {
+        return images;
+    }
+
+    let now = Utc::now();
+
+    let checked_recently = images
+        .site_checked_at
+        .and_then(|time| DateTime::from_timestamp(time, 0))
+        .is_some_and(|time| now - time < SITE_IMAGES_RECHECK_INTERVAL);
+
+    if checked_recently {
+        return images;
+    }
+
+    images.site_checked_at = Some(now.timestamp());
+
+    let site_url = feed
+        .site_url
+        .as_deref()
+        .and_then(resolve)
+        .unwrap_or_else(|| feed_url.join("/").unwrap_or_else(|_| feed_url.clone()));
+
+    match fetch_page_content(clients, host_limiter, policy, options, site_url.as_str()).await {
+        Ok(html) => {
+            let site_images = site_metadata::find_site_images(&html, &site_url);
+            images.icon = images.icon.or(site_images.icon);
+            images.thumbnail = images.thumbnail.or(site_images.image);
         }
+        Err(e) => tracing::warn!(%site_url, "failed to fetch website: {e:?}"),
+    }
 
-        Ok(())
+    if images.icon.is_none()
+        && let Some(favicon_url) = site_metadata::default_favicon_url(&site_url)
+        && url_exists(clients, host_limiter, policy, options, &favicon_url).await
+    {
+        images.icon = Some(favicon_url.into());
+    }
+
+    images
+}
+
+async fn url_exists(
+    clients: &FeedClients,
+    host_limiter: &HostLimiter,
+    policy: &FetchPolicy,
+    options: &RequestOptions,
+    url: &Url,
+) -> bool {
+    send_page_request(
+        clients,
+        host_limiter,
+        policy,
+        options,
+        Method::HEAD,
+        url.clone(),
+    )
+    .await
+    .is_ok_and(|(res, _)| res.status().is_success())
+}
+
+/// Requests a page, following redirects by hand so that every hop is checked
+/// against the policy and only gets the feed's credentials if it is entitled
+/// to them.
+async fn send_page_request(
+    clients: &FeedClients,
+    host_limiter: &HostLimiter,
+    policy: &FetchPolicy,
+    options: &RequestOptions,
+    method: Method,
+    url: Url,
+) -> eyre::Result<(reqwest::Response, OwnedSemaphorePermit)> {
+    let mut current_url = url.clone();
+
+    for _ in 0..=MAX_REDIRECTS {
+        policy.check_url(&current_url)?;
+
+        let client = clients.get(&current_url);
+        let req = options
+            .apply(
+                client.request(method.clone(), current_url.clone()),
+                &current_url,
+            )
+            .build()?;
+        let permit = host_limiter.acquire(req.url()).await?;
+        let res = client.execute(req).await?;
+
+        host_limiter.check_response(&res)?;
+
+        match redirect_location(&res) {
+            Some(location) => current_url = current_url.join(location)?,
+            None => return Ok((res, permit)),
+        }
     }
+
+    Err(eyre!("too many redirects fetching {url}"))
 }
 
-async fn fetch_page_content(client: &Client, url: &str) -> eyre::Result<String> {
-    let text = client.get(url).send().await?.text().await?;
-    Ok(text)
+/// Where a response redirects to, if anywhere.
+fn redirect_location(res: &reqwest::Response) -> Option<&str> {
+    res.headers()
+        .get(LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| res.status().is_redirection() && res.status() != StatusCode::NOT_MODIFIED)
 }
 
-#[derive(Debug)]
-enum Feed {
-    Atom(Box<atom_syndication::Feed>),
-    Rss(Box<rss::Channel>),
+/// Validators from a previous response, used to make conditional requests.
+#[derive(Debug, Default)]
+struct CacheHeaders {
+    etag: Option<String>,
+    last_modified: Option<String>,
 }
 
-#[derive(Error, Debug)]
-#[error("Failed to parse feed")]
-struct FeedParseError {
-    atom: atom_syndication::Error,
-    rss: rss::Error,
-}
-
-async fn fetch_feed(client: &Client, url: &str) -> eyre::Result<Feed> {
-    let res = client.get(url).send().await?;
-
-    tracing::trace!(
-        "Fetched feed content from {url} with status: {}",
-        res.status().as_str()
-    );
-
-    let content = res.bytes().await?;
-
-    match atom_syndication::Feed::read_from(&content[..]) {
-        Ok(feed) => Ok(Feed::Atom(Box::new(feed))),
-        Err(atom_error) => match rss::Channel::read_from(&content[..]) {
-            Ok(channel) => Ok(Feed::Rss(Box::new(channel))),
-            Err(rss_error) => {
-                let content = String::from_utf8_lossy(&content);
-                debug!("{content}");
-                debug!("Failed to parse as Atom feed: {atom_error}");
-                debug!("Failed to parse as RSS feed: {rss_error}");
-                Err(eyre!(FeedParseError {
-                    atom: atom_error,
-                    rss: rss_error,
-                }))
+impl CacheHeaders {
+    fn from_response(res: &reqwest::Response) -> CacheHeaders {
+        let header = |name| {
+            res.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        CacheHeaders {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+/// The most redirects followed when fetching a feed or page.
+const MAX_REDIRECTS: usize = 10;
+
+struct FeedFetch {
+    outcome: FetchOutcome,
+    /// Where the feed has moved to, if it was only reached through permanent
+    /// redirects.
+    moved_to: Option<Url>,
+}
+
+enum FetchOutcome {
+    NotModified,
+    /// The feed has been removed for good (`410 Gone`).
+    Gone,
+    Fetched(Box<ParsedFeed>, CacheHeaders),
+}
+
+/// Fetches and parses a feed, following redirects itself so that permanent
+/// ones can be told apart from temporary ones.
+async fn fetch_feed(
+    clients: &FeedClients,
+    host_limiter: &HostLimiter,
+    policy: &FetchPolicy,
+    options: &RequestOptions,
+    url: &str,
+    cache_headers: &CacheHeaders,
+) -> eyre::Result<FeedFetch> {
+    let mut current_url = Url::parse(url)?;
+    let mut redirects = 0;
+    let mut permanent = true;
+
+    let (res, _permit) = loop {
+        // Redirects are checked too, as they could point anywhere.
+        policy.check_url(&current_url)?;
+
+        let client = clients.get(&current_url);
+        let mut req = options.apply(client.get(current_url.clone()), &current_url);
+
+        if let Some(etag) = &cache_headers.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &cache_headers.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let req = req.build()?;
+        let permit = host_limiter.acquire(req.url()).await?;
+        let res = client.execute(req).await?;
+
+        host_limiter.check_response(&res)?;
+
+        tracing::trace!(
+            "Fetched feed content from {current_url} with status: {}",
+            res.status().as_str()
+        );
+
+        let Some(location) = redirect_location(&res) else {
+            break (res, permit);
+        };
+
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(eyre!("too many redirects fetching {url}"));
+        }
+
+        permanent &= matches!(
+            res.status(),
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        current_url = current_url.join(location)?;
+    };
+
+    let moved_to = (redirects > 0 && permanent).then(|| current_url.clone());
+
+    if res.status() == StatusCode::NOT_MODIFIED {
+        return Ok(FeedFetch {
+            outcome: FetchOutcome::NotModified,
+            moved_to,
+        });
+    }
+
+    if res.status() == StatusCode::GONE {
+        return Ok(FeedFetch {
+            outcome: FetchOutcome::Gone,
+            moved_to,
+        });
+    }
+
+    // Error pages aren't worth parsing, and the status says more about what
+    // went wrong than a parse error would.
+    let res = res.error_for_status()?;
+
+    let cache_headers = CacheHeaders::from_response(&res);
+    let page_url = res.url().clone();
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(ToOwned::to_owned);
+    let content = policy.read_body(res).await?;
+
+    let text = charset::decode_feed(&content, content_type.as_deref());
+
+    match parser::parse_feed(text.as_bytes(), &page_url) {
+        Ok(feed) => Ok(FeedFetch {
+            outcome: FetchOutcome::Fetched(Box::new(feed), cache_headers),
+            moved_to,
+        }),
+        Err(e) => {
+            if discovery::is_html(content_type.as_deref(), &content) {
+                return Err(eyre!(HtmlPage {
+                    url: page_url,
+                    content: charset::decode_html(&content, content_type.as_deref()),
+                }));
             }
-        },
+
+            debug!("{text}");
+            Err(eyre!(e))
+        }
     }
 }
 
+/// Returned by [`fetch_feed`] when the url points at a web page instead of a
+/// feed.
+#[derive(Error, Debug)]
+#[error("{url} is an HTML page, not a feed")]
+struct HtmlPage {
+    url: Url,
+    content: String,
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -0,0 +1,579 @@
+//! Parses feed documents of any supported format into a common [`ParsedFeed`],
+//! so that syncing doesn't need to know which format a feed uses.
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, FixedOffset, Utc};
+use reqwest::Url;
+use scraper::Html;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::debug;
+
+use crate::schedule::FeedHints;
+use crate::{dates, json_feed, podcast, rdf, thumbnails};
+
+#[derive(Error, Debug)]
+#[error(
+    "Failed to parse feed as Atom ({atom}), RSS 1.0 ({rdf}), RSS 2.0 ({rss}) or JSON Feed ({json})"
+)]
+pub struct FeedParseError {
+    atom: atom_syndication::Error,
+    rdf: Box<rdf::Error>,
+    rss: rss::Error,
+    json: serde_json::Error,
+}
+
+#[derive(Debug)]
+pub struct ParsedFeed {
+    pub title: String,
+    /// The url of the website the feed belongs to.
+    pub site_url: Option<String>,
+    /// A small square icon for the feed.
+    pub icon: Option<String>,
+    /// A larger image or logo representing the feed.
+    pub logo: Option<String>,
+    /// Whether the feed looks like a podcast, based on its entries having
+    /// audio or video enclosures.
+    pub is_podcast: bool,
+    pub hints: FeedHints,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug)]
+pub struct FeedEntry {
+    /// Identifies the entry within its feed. Entries without an id of their
+    /// own are identified by their url, or failing that, their content.
+    pub guid: String,
+    pub url: Option<String>,
+    pub title: String,
+    /// A plain text summary.
+    pub description: Option<String>,
+    /// The full HTML content, if the feed includes it.
+    pub content: Option<String>,
+    pub published: Option<DateTime<FixedOffset>>,
+    pub updated: Option<DateTime<FixedOffset>>,
+    pub author: Option<String>,
+    pub thumbnail: Option<String>,
+    pub enclosures: Vec<podcast::Enclosure>,
+}
+
+impl ParsedFeed {
+    /// The publish times of the feed's entries, for estimating how often it
+    /// is updated.
+    pub fn post_times(&self) -> Vec<DateTime<Utc>> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.published)
+            .map(|t| t.to_utc())
+            .collect()
+    }
+}
+
+/// Parses a feed document, trying each supported format in turn. Relative
+/// urls of thumbnails are resolved against `url`, the url the document was
+/// fetched from.
+pub fn parse_feed(content: &[u8], url: &Url) -> Result<ParsedFeed, FeedParseError> {
+    let atom_error = match atom_syndication::Feed::read_from(content) {
+        Ok(feed) => return Ok(from_atom(feed, url)),
+        Err(e) => e,
+    };
+
+    // The rss crate accepts some RSS 1.0 documents too, but loses their
+    // Dublin Core metadata, so they get their own parser first.
+    let rdf_error = match rdf::Feed::read_from(content) {
+        Ok(feed) => return Ok(from_rdf(feed, url)),
+        Err(e) => e,
+    };
+
+    let rss_error = match rss::Channel::read_from(content) {
+        Ok(channel) => return Ok(from_rss(channel, url)),
+        Err(e) => e,
+    };
+
+    let json_error = match json_feed::Feed::read_from(content) {
+        Ok(feed) => return Ok(from_json(feed, url)),
+        Err(e) => e,
+    };
+
+    debug!("Failed to parse as Atom feed: {atom_error}");
+    debug!("Failed to parse as RSS 1.0 feed: {rdf_error}");
+    debug!("Failed to parse as RSS feed: {rss_error}");
+    debug!("Failed to parse as JSON feed: {json_error}");
+
+    Err(FeedParseError {
+        atom: atom_error,
+        rdf: Box::new(rdf_error),
+        rss: rss_error,
+        json: json_error,
+    })
+}
+
+fn from_atom(feed: atom_syndication::Feed, url: &Url) -> ParsedFeed {
+    let entries = feed
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let description = entry.summary().map(|summary| html_to_text(&summary.value));
+
+            let content_url = entry
+                .links
+                .iter()
+                .find(|link| {
+                    link.rel == "alternate" && link.mime_type.as_deref() == Some("text/html")
+                })
+                .or_else(|| entry.links.iter().find(|link| link.rel == "alternate"))
+                .or_else(|| entry.links.first())
+                .map(|link| link.href.clone())
+                .or_else(|| entry.id.starts_with("http").then(|| entry.id.clone()));
+
+            let enclosures = podcast::Enclosure::from_atom_entry(&entry);
+            let author = entry.authors.first().map(|author| author.name.clone());
+            let thumbnail =
+                thumbnails::from_atom_entry(&entry, &post_base_url(url, content_url.as_deref()));
+
+            let guid = if entry.id.is_empty() {
+                content_hash(&[
+                    Some(&entry.title.value),
+                    content_url.as_deref(),
+                    description.as_deref(),
+                ])
+            } else {
+                entry.id
+            };
+
+            FeedEntry {
+                guid,
+                url: post_url(url, content_url.as_deref()),
+                title: entry.title.value,
+                description,
+                content: entry.content.and_then(|content| content.value),
+                published: Some(entry.published.unwrap_or(entry.updated)),
+                updated: Some(entry.updated),
+                author,
+                thumbnail,
+                enclosures,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ParsedFeed {
+        title: feed.title.value,
+        site_url: feed
+            .links
+            .into_iter()
+            .find(|link| link.rel == "alternate")
+            .map(|link| link.href),
+        icon: feed.icon,
+        logo: feed.logo,
+        is_podcast: entries.iter().any(has_media),
+        hints: FeedHints::default(),
+        entries,
+    }
+}
+
+fn from_rss(channel: rss::Channel, url: &Url) -> ParsedFeed {
+    let hints = FeedHints::from_rss(&channel);
+    let has_itunes = channel.itunes_ext.is_some();
+
+    let rss::Channel {
+        title,
+        link,
+        image,
+        items,
+        namespaces,
+        ..
+    } = channel;
+
+    let entries = items
+        .into_iter()
+        .map(|item| {
+            let published = dates::rss_item_published(&item, &namespaces);
+            let updated = dates::rss_item_updated(&item, &namespaces);
+
+            let enclosures = podcast::Enclosure::from_rss_item(&item)
+                .into_iter()
+                .collect();
+            let author = item
+                .dublin_core_ext
+                .as_ref()
+                .and_then(|dc| dc.creators.first().cloned())
+                .or_else(|| item.author.clone());
+            let thumbnail =
+                thumbnails::from_rss_item(&item, &post_base_url(url, item.link.as_deref()));
+
+            let description = item.description.as_deref().map(html_to_text);
+
+            // Items without a guid are identified by their link, or if they
+            // don't have one either, by their content.
+            let guid = item
+                .guid
+                .map(|guid| guid.value)
+                .filter(|guid| !guid.is_empty())
+                .or_else(|| item.link.clone())
+                .unwrap_or_else(|| {
+                    content_hash(&[
+                        item.title.as_deref(),
+                        item.description.as_deref(),
+                        item.pub_date.as_deref(),
+                    ])
+                });
+
+            FeedEntry {
+                guid,
+                url: post_url(url, item.link.as_deref()),
+                title: item.title.unwrap_or_else(|| "Untitled".to_owned()),
+                description,
+                content: None,
+                published,
+                updated,
+                author,
+                thumbnail,
+                enclosures,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ParsedFeed {
+        title,
+        site_url: Some(link).filter(|link| !link.is_empty()),
+        icon: None,
+        logo: image.map(|image| image.url),
+        is_podcast: has_itunes || entries.iter().any(has_media),
+        hints,
+        entries,
+    }
+}
+
+fn from_rdf(feed: rdf::Feed, url: &Url) -> ParsedFeed {
+    let hints = FeedHints::from_rdf(&feed);
+
+    let entries = feed
+        .items
+        .into_iter()
+        .map(|item| {
+            let link = item
+                .link
+                .clone()
+                .or_else(|| item.about.clone().filter(|about| about.starts_with("http")));
+
+            let thumbnail = thumbnails::from_rdf_item(&item, &post_base_url(url, link.as_deref()));
+
+            let description = item.description.as_deref().map(html_to_text);
+
+            let guid = item
+                .about
+                .clone()
+                .or_else(|| link.clone())
+                .unwrap_or_else(|| {
+                    content_hash(&[
+                        item.title.as_deref(),
+                        item.description.as_deref(),
+                        item.date.as_deref(),
+                    ])
+                });
+
+            FeedEntry {
+                guid,
+                url: post_url(url, link.as_deref()),
+                title: item.title.unwrap_or_else(|| "Untitled".to_owned()),
+                description,
+                content: item.content,
+                published: item.date.as_deref().and_then(dates::parse_date),
+                updated: None,
+                author: item.creator,
+                thumbnail,
+                enclosures: vec![],
+            }
+        })
+        .collect();
+
+    ParsedFeed {
+        title: feed.title,
+        site_url: feed.link,
+        icon: None,
+        logo: feed.image,
+        is_podcast: false,
+        hints,
+        entries,
+    }
+}
+
+fn from_json(feed: json_feed::Feed, url: &Url) -> ParsedFeed {
+    let entries = feed
+        .items
+        .into_iter()
+        .map(|item| {
+            let enclosures = podcast::Enclosure::from_json_item(&item);
+            let author = item.author_name().map(ToOwned::to_owned);
+            let thumbnail =
+                thumbnails::from_json_item(&item, &post_base_url(url, item.url.as_deref()));
+
+            let published = item.date_published.as_deref().and_then(dates::parse_date);
+            let updated = item.date_modified.as_deref().and_then(dates::parse_date);
+
+            let description = item
+                .summary
+                .or_else(|| item.content_text.clone())
+                .or_else(|| item.content_html.as_deref().map(html_to_text));
+
+            FeedEntry {
+                guid: item.id.into_string(),
+                url: post_url(url, item.url.as_deref().or(item.external_url.as_deref())),
+                title: item.title.unwrap_or_else(|| "Untitled".to_owned()),
+                description,
+                content: item.content_html.or(item.content_text),
+                published: published.or(updated),
+                updated,
+                author,
+                thumbnail,
+                enclosures,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ParsedFeed {
+        title: feed.title,
+        site_url: feed.home_page_url,
+        icon: feed.favicon,
+        logo: feed.icon,
+        is_podcast: entries.iter().any(has_media),
+        hints: FeedHints::default(),
+        entries,
+    }
+}
+
+fn has_media(entry: &FeedEntry) -> bool {
+    entry
+        .enclosures
+        .iter()
+        .any(|enclosure| enclosure.is_media())
+}
+
+fn html_to_text(html: &str) -> String {
+    let html = Html::parse_fragment(html);
+    html.root_element().text().collect()
+}
+
+/// Resolves a post's link against the feed's url, leaving out links that
+/// aren't valid urls even then.
+fn post_url(feed_url: &Url, post_url: Option<&str>) -> Option<String> {
+    post_url
+        .and_then(|url| feed_url.join(url).ok())
+        .map(String::from)
+}
+
+/// The url that relative urls in a post are resolved against.
+fn post_base_url(feed_url: &Url, post_url: Option<&str>) -> Url {
+    post_url
+        .and_then(|url| feed_url.join(url).ok())
+        .unwrap_or_else(|| feed_url.clone())
+}
+
+/// Identifies an entry that doesn't have a usable id of its own.
+fn content_hash(parts: &[Option<&str>]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.unwrap_or_default());
+        hasher.update([0]);
+    }
+    Base64UrlUnpadded::encode_string(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn parse_fixture(name: &str, url: &str) -> Result<ParsedFeed, FeedParseError> {
+        let path = format!("{}/tests/fixtures/feeds/{name}", env!("CARGO_MANIFEST_DIR"));
+        let content = std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+        parse_feed(&content, &Url::parse(url).unwrap())
+    }
+
+    fn date(s: &str) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap())
+    }
+
+    #[test]
+    fn parses_atom() {
+        let feed = parse_fixture("atom.xml", "https://example.com/atom.xml").unwrap();
+
+        assert_eq!(feed.title, "Example Atom Feed");
+        assert_eq!(feed.site_url.as_deref(), Some("https://example.com/"));
+        assert_eq!(feed.icon.as_deref(), Some("/favicon.png"));
+        assert_eq!(feed.logo.as_deref(), Some("/logo.png"));
+        assert!(feed.is_podcast);
+        assert_eq!(feed.entries.len(), 2);
+
+        let first = &feed.entries[0];
+        assert_eq!(first.guid, "urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a");
+        assert_eq!(
+            first.url.as_deref(),
+            Some("https://example.com/posts/first")
+        );
+        assert_eq!(first.title, "First post");
+        assert_eq!(first.description.as_deref(), Some("A short summary"));
+        assert_eq!(
+            first.content.as_deref(),
+            Some("<p>Full content</p><img src=\"images/first.png\">")
+        );
+        assert_eq!(first.published, date("2024-03-01T09:30:00+01:00"));
+        assert_eq!(first.updated, date("2024-03-02T10:00:00Z"));
+        assert_eq!(first.author.as_deref(), Some("Alice"));
+        assert_eq!(
+            first.thumbnail.as_deref(),
+            Some("https://example.com/posts/images/first.png")
+        );
+        assert!(first.enclosures.is_empty());
+
+        // Entries without an id get a stable one derived from their content.
+        let episode = &feed.entries[1];
+        assert_eq!(episode.guid.len(), 43);
+        assert_eq!(
+            parse_fixture("atom.xml", "https://example.com/atom.xml")
+                .unwrap()
+                .entries[1]
+                .guid,
+            episode.guid
+        );
+        assert_eq!(episode.published, date("2024-02-20T08:00:00Z"));
+        assert_eq!(
+            episode.thumbnail.as_deref(),
+            Some("https://cdn.example.com/episode.jpg")
+        );
+        assert_eq!(
+            episode.enclosures,
+            [podcast::Enclosure {
+                url: "https://example.com/episode.mp3".to_owned(),
+                mime_type: Some("audio/mpeg".to_owned()),
+                length: Some(1234),
+                duration: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_rss() {
+        let feed = parse_fixture("rss.xml", "https://example.com/feed.xml").unwrap();
+
+        assert_eq!(feed.title, "Example Podcast");
+        assert_eq!(feed.site_url.as_deref(), Some("https://example.com/"));
+        assert_eq!(feed.icon, None);
+        assert_eq!(feed.logo.as_deref(), Some("https://example.com/cover.jpg"));
+        assert!(feed.is_podcast);
+        assert_eq!(feed.hints.min_interval, Some(TimeDelta::minutes(120)));
+        assert_eq!(feed.entries.len(), 3);
+
+        let episode = &feed.entries[0];
+        assert_eq!(episode.guid, "episode-2");
+        assert_eq!(episode.title, "Episode 2");
+        assert_eq!(episode.description.as_deref(), Some("Second episode"));
+        assert_eq!(episode.content, None);
+        assert_eq!(episode.published, date("2024-03-05T07:00:00Z"));
+        assert_eq!(episode.author.as_deref(), Some("Bob"));
+        assert_eq!(
+            episode.enclosures,
+            [podcast::Enclosure {
+                url: "https://example.com/episodes/2.mp3".to_owned(),
+                mime_type: Some("audio/mpeg".to_owned()),
+                length: Some(5678),
+                duration: Some(3723),
+            }]
+        );
+
+        // Relative links are resolved against the feed's url, and images
+        // against the post's.
+        let first = &feed.entries[1];
+        assert_eq!(first.guid, "/episodes/1");
+        assert_eq!(first.url.as_deref(), Some("https://example.com/episodes/1"));
+        assert_eq!(first.published, date("2024-03-04T07:00:00-05:00"));
+        assert_eq!(
+            first.thumbnail.as_deref(),
+            Some("https://example.com/episodes/cover-1.jpg")
+        );
+
+        let untitled = &feed.entries[2];
+        assert_eq!(untitled.title, "Untitled");
+        assert_eq!(untitled.url, None);
+        assert_eq!(untitled.published, None);
+        assert_eq!(untitled.guid.len(), 43);
+
+        assert_eq!(feed.post_times().len(), 2);
+    }
+
+    #[test]
+    fn parses_rdf() {
+        let feed = parse_fixture("rdf.xml", "https://example.org/index.rdf").unwrap();
+
+        assert_eq!(feed.title, "Example RDF Feed");
+        assert_eq!(feed.site_url.as_deref(), Some("https://example.org/"));
+        assert_eq!(feed.logo.as_deref(), Some("https://example.org/logo.gif"));
+        assert!(!feed.is_podcast);
+        assert_eq!(feed.hints.min_interval, Some(TimeDelta::minutes(30)));
+        assert_eq!(feed.entries.len(), 1);
+
+        let article = &feed.entries[0];
+        assert_eq!(article.guid, "https://example.org/articles/1");
+        assert_eq!(
+            article.url.as_deref(),
+            Some("https://example.org/articles/1")
+        );
+        assert_eq!(article.title, "An article");
+        assert_eq!(article.description.as_deref(), Some("Plain description"));
+        assert_eq!(
+            article.content.as_deref(),
+            Some("<p>Rich <img src=\"/img/1.png\"> content</p>")
+        );
+        assert_eq!(article.published, date("2024-01-15T12:00:00+09:00"));
+        assert_eq!(article.author.as_deref(), Some("Carol"));
+        assert_eq!(
+            article.thumbnail.as_deref(),
+            Some("https://example.org/img/1.png")
+        );
+    }
+
+    #[test]
+    fn parses_json_feed() {
+        let feed = parse_fixture("json-feed.json", "https://example.net/feed.json").unwrap();
+
+        assert_eq!(feed.title, "Example JSON Feed");
+        assert_eq!(feed.site_url.as_deref(), Some("https://example.net/"));
+        assert_eq!(
+            feed.icon.as_deref(),
+            Some("https://example.net/favicon.ico")
+        );
+        assert_eq!(feed.logo.as_deref(), Some("https://example.net/icon.png"));
+        assert!(feed.is_podcast);
+        assert_eq!(feed.entries.len(), 3);
+
+        let note = &feed.entries[0];
+        assert_eq!(note.guid, "42");
+        assert_eq!(note.title, "A note");
+        assert_eq!(note.description.as_deref(), Some("Hello world"));
+        assert_eq!(
+            note.content.as_deref(),
+            Some("<p>Hello <strong>world</strong></p>")
+        );
+        assert_eq!(note.published, date("2024-04-01T10:00:00-07:00"));
+        assert_eq!(note.updated, date("2024-04-02T10:00:00-07:00"));
+        assert_eq!(note.author.as_deref(), Some("Dave"));
+        assert_eq!(note.enclosures[0].duration, Some(62));
+
+        let untitled = &feed.entries[1];
+        assert_eq!(untitled.title, "Untitled");
+        assert_eq!(untitled.description.as_deref(), Some("Just text"));
+        assert_eq!(untitled.content.as_deref(), Some("Just text"));
+        assert_eq!(untitled.published, None);
+
+        // Dates without an offset are taken to be in UTC rather than
+        // rejecting the whole feed.
+        let naive = &feed.entries[2];
+        assert_eq!(naive.published, date("2024-04-03T10:00:00Z"));
+    }
+
+    #[test]
+    fn rejects_html() {
+        assert!(parse_fixture("not-a-feed.html", "https://example.com/").is_err());
+    }
+}
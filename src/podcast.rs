@@ -0,0 +1,93 @@
+//! Enclosures attached to feed entries, as used by podcasts.
+
+use crate::json_feed;
+
+/// An enclosure read from a feed, before it is stored.
+#[derive(Debug, PartialEq)]
+pub struct Enclosure {
+    pub url: String,
+    pub mime_type: Option<String>,
+    /// Size in bytes.
+    pub length: Option<i64>,
+    /// Duration in seconds.
+    pub duration: Option<i64>,
+}
+
+impl Enclosure {
+    pub fn from_rss_item(item: &rss::Item) -> Option<Enclosure> {
+        let enclosure = item.enclosure.as_ref()?;
+
+        Some(Enclosure {
+            url: enclosure.url.clone(),
+            mime_type: non_empty(&enclosure.mime_type),
+            length: parse_length(&enclosure.length),
+            duration: item
+                .itunes_ext
+                .as_ref()
+                .and_then(|itunes| itunes.duration.as_deref())
+                .and_then(parse_duration),
+        })
+    }
+
+    pub fn from_atom_entry(entry: &atom_syndication::Entry) -> Vec<Enclosure> {
+        entry
+            .links
+            .iter()
+            .filter(|link| link.rel == "enclosure")
+            .map(|link| Enclosure {
+                url: link.href.clone(),
+                mime_type: link.mime_type.as_deref().and_then(non_empty),
+                length: link.length.as_deref().and_then(parse_length),
+                duration: None,
+            })
+            .collect()
+    }
+
+    pub fn from_json_item(item: &json_feed::Item) -> Vec<Enclosure> {
+        item.attachments
+            .iter()
+            .map(|attachment| Enclosure {
+                url: attachment.url.clone(),
+                mime_type: non_empty(&attachment.mime_type),
+                length: attachment.size_in_bytes.filter(|&size| size > 0),
+                duration: attachment
+                    .duration_in_seconds
+                    .filter(|&duration| duration > 0.0)
+                    .map(|duration| duration.round() as i64),
+            })
+            .collect()
+    }
+
+    /// Whether this is audio or video, as opposed to e.g. an image.
+    pub fn is_media(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|t| t.starts_with("audio/") || t.starts_with("video/"))
+    }
+}
+
+/// Parses an `itunes:duration`, which is either a number of seconds or a
+/// `[HH:]MM:SS` timestamp.
+fn parse_duration(duration: &str) -> Option<i64> {
+    let mut seconds = 0;
+    let mut parts = 0;
+
+    for part in duration.trim().split(':') {
+        // Some feeds include fractional seconds.
+        let part = part.split('.').next()?;
+        seconds = seconds * 60 + part.parse::<i64>().ok()?;
+        parts += 1;
+    }
+
+    (parts <= 3 && seconds > 0).then_some(seconds)
+}
+
+/// Feeds often use a length of 0 when they don't know it.
+fn parse_length(length: &str) -> Option<i64> {
+    length.trim().parse().ok().filter(|&length| length > 0)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_ascii_lowercase())
+}
@@ -0,0 +1,234 @@
+//! A parser for [RSS 1.0](https://web.resource.org/rss/1.0/spec) and RSS 0.90
+//! feeds, which are RDF documents rather than RSS 2.0 `<rss>` documents.
+//!
+//! Elements are matched by namespace rather than by prefix, since RDF feeds in
+//! the wild don't always use the conventional prefixes.
+
+use quick_xml::NsReader;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::ResolveResult;
+use thiserror::Error;
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RSS_1_0_NS: &str = "http://purl.org/rss/1.0/";
+const RSS_0_90_NS: &str = "http://my.netscape.com/rdf/simple/0.9/";
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const CONTENT_NS: &str = "http://purl.org/rss/1.0/modules/content/";
+const SY_NS: &str = "http://purl.org/rss/1.0/modules/syndication/";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error("root element is not rdf:RDF")]
+    NotRdf,
+    #[error("missing channel element")]
+    MissingChannel,
+}
+
+#[derive(Debug)]
+pub struct Feed {
+    pub title: String,
+    pub link: Option<String>,
+    pub image: Option<String>,
+    /// `sy:updatePeriod`, e.g. `hourly`.
+    pub update_period: Option<String>,
+    /// `sy:updateFrequency`, the number of updates per period.
+    pub update_frequency: Option<String>,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug)]
+pub struct Item {
+    /// The `rdf:about` identifier of the item, usually its url.
+    pub about: Option<String>,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    /// `content:encoded`
+    pub content: Option<String>,
+    /// `dc:date`, a W3CDTF (ISO 8601) timestamp.
+    pub date: Option<String>,
+    /// `dc:creator`
+    pub creator: Option<String>,
+}
+
+impl Feed {
+    pub fn read_from(content: &[u8]) -> Result<Feed, Error> {
+        let root = parse_document(content)?;
+
+        if !root.is(RDF_NS, "RDF") {
+            return Err(Error::NotRdf);
+        }
+
+        let channel = root
+            .children
+            .iter()
+            .find(|el| el.is_rss("channel"))
+            .ok_or(Error::MissingChannel)?;
+
+        // In RSS 1.0 the image and items are siblings of the channel rather
+        // than children.
+        let image = root
+            .children
+            .iter()
+            .find(|el| el.is_rss("image"))
+            .and_then(|image| image.rss_child("url"));
+
+        let items = root
+            .children
+            .iter()
+            .filter(|el| el.is_rss("item"))
+            .map(|item| Item {
+                about: item.attr(RDF_NS, "about").map(ToOwned::to_owned),
+                title: item.rss_child("title"),
+                link: item.rss_child("link"),
+                description: item
+                    .rss_child("description")
+                    .or_else(|| item.child_text(DC_NS, "description")),
+                content: item.child_text(CONTENT_NS, "encoded"),
+                date: item.child_text(DC_NS, "date"),
+                creator: item.child_text(DC_NS, "creator"),
+            })
+            .collect();
+
+        Ok(Feed {
+            title: channel.rss_child("title").unwrap_or_default(),
+            link: channel.rss_child("link"),
+            image,
+            update_period: channel.child_text(SY_NS, "updatePeriod"),
+            update_frequency: channel.child_text(SY_NS, "updateFrequency"),
+            items,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    namespace: Option<String>,
+    name: String,
+    attributes: Vec<(Option<String>, String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace.as_deref() == Some(namespace) && self.name == name
+    }
+
+    fn is_rss(&self, name: &str) -> bool {
+        self.name == name && matches!(self.namespace.as_deref(), Some(RSS_1_0_NS | RSS_0_90_NS))
+    }
+
+    fn attr(&self, namespace: &str, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(ns, local, _)| ns.as_deref() == Some(namespace) && local == name)
+            .map(|(_, _, value)| value.as_str())
+    }
+
+    fn child_text(&self, namespace: &str, name: &str) -> Option<String> {
+        self.children
+            .iter()
+            .find(|el| el.is(namespace, name))
+            .map(|el| el.text.trim().to_owned())
+            .filter(|text| !text.is_empty())
+    }
+
+    fn rss_child(&self, name: &str) -> Option<String> {
+        self.child_text(RSS_1_0_NS, name)
+            .or_else(|| self.child_text(RSS_0_90_NS, name))
+    }
+}
+
+/// Reads a whole XML document into a tree of elements.
+fn parse_document(content: &[u8]) -> Result<Element, Error> {
+    let mut reader = NsReader::from_reader(content);
+    let mut stack: Vec<Element> = vec![];
+
+    loop {
+        let (namespace, event) = reader.read_resolved_event()?;
+        let namespace = match namespace {
+            ResolveResult::Bound(ns) => Some(String::from_utf8_lossy(ns.as_ref()).into_owned()),
+            _ => None,
+        };
+
+        match event {
+            Event::Start(start) => {
+                let element = read_element(&reader, namespace, &start)?;
+
+                // Bail out early on other kinds of XML documents.
+                if stack.is_empty() && !element.is(RDF_NS, "RDF") {
+                    return Err(Error::NotRdf);
+                }
+
+                stack.push(element);
+            }
+            Event::Empty(start) => {
+                let element = read_element(&reader, namespace, &start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::End(_) => {
+                let Some(element) = stack.pop() else {
+                    return Err(Error::NotRdf);
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    // Undefined HTML entities are common in feeds, so keep the
+                    // raw text rather than failing.
+                    match text.unescape() {
+                        Ok(text) => element.text.push_str(&text),
+                        Err(_) => element.text.push_str(&String::from_utf8_lossy(&text)),
+                    }
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::Eof => return Err(Error::NotRdf),
+            _ => {}
+        }
+    }
+}
+
+fn read_element(
+    reader: &NsReader<&[u8]>,
+    namespace: Option<String>,
+    start: &BytesStart,
+) -> Result<Element, Error> {
+    let mut attributes = vec![];
+
+    for attr in start.attributes() {
+        let attr = attr.map_err(quick_xml::Error::from)?;
+        let (ns, local) = reader.resolve_attribute(attr.key);
+        let ns = match ns {
+            ResolveResult::Bound(ns) => Some(String::from_utf8_lossy(ns.as_ref()).into_owned()),
+            _ => None,
+        };
+
+        attributes.push((
+            ns,
+            String::from_utf8_lossy(local.as_ref()).into_owned(),
+            attr.decode_and_unescape_value(reader.decoder())?
+                .into_owned(),
+        ));
+    }
+
+    Ok(Element {
+        namespace,
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attributes,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,315 @@
+//! A small readability-style extractor that finds the main article body of a
+//! web page.
+//!
+//! Candidate containers are scored by the paragraphs they contain, with hints
+//! taken from their class names and ids. The winning container is serialized
+//! back to HTML with only a safe subset of tags and attributes kept, and with
+//! relative urls resolved against the page url.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ego_tree::NodeRef;
+use reqwest::Url;
+use scraper::node::Element;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Extracted articles shorter than this are assumed to be a misdetection.
+const MIN_ARTICLE_LENGTH: usize = 250;
+/// Paragraphs shorter than this don't count towards a container's score.
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "body", "content", "entry", "main", "page", "post", "story", "text",
+];
+const NEGATIVE_HINTS: &[&str] = &[
+    "ad-", "banner", "combx", "comment", "footer", "footnote", "masthead", "menu", "meta", "nav",
+    "popup", "promo", "related", "share", "sidebar", "social", "sponsor", "widget",
+];
+
+/// Elements that are dropped along with their contents.
+const DROPPED_TAGS: &[&str] = &[
+    "aside", "button", "footer", "form", "header", "iframe", "input", "nav", "noscript", "object",
+    "script", "select", "style", "svg", "textarea",
+];
+/// Elements that are kept in the output. Anything else is replaced by its
+/// children.
+const KEPT_TAGS: &[&str] = &[
+    "a",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "dd",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+const VOID_TAGS: &[&str] = &["br", "hr", "img"];
+
+/// Extracts the main article body of a page as sanitized HTML.
+pub fn extract_article(document: &Html, page_url: &Url) -> Option<String> {
+    let article = find_article(document)?;
+
+    let mut html = String::new();
+    for child in article.children() {
+        serialize(child, page_url, &mut html);
+    }
+
+    let text_len = article.text().map(|t| t.trim().len()).sum::<usize>();
+    if text_len < MIN_ARTICLE_LENGTH {
+        return None;
+    }
+
+    Some(html)
+}
+
+fn find_article(document: &Html) -> Option<ElementRef<'_>> {
+    // Pages marked up with explicit article bodies don't need any guesswork.
+    let explicit = Selector::parse("[itemprop=\"articleBody\"], article").unwrap();
+    let explicit = document
+        .select(&explicit)
+        .max_by_key(|el| el.text().map(str::len).sum::<usize>());
+
+    if let Some(explicit) = explicit
+        && explicit.text().map(str::len).sum::<usize>() >= MIN_ARTICLE_LENGTH
+    {
+        return Some(explicit);
+    }
+
+    let paragraphs = Selector::parse("p, pre, td").unwrap();
+    let mut scores: HashMap<_, f64> = HashMap::new();
+
+    for paragraph in document.select(&paragraphs) {
+        let text = paragraph.text().collect::<String>();
+        let text = text.trim();
+
+        if text.len() < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() / 100).min(3) as f64;
+
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores
+                .entry(parent.id())
+                .or_insert_with(|| class_weight(parent)) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores
+                .entry(grandparent.id())
+                .or_insert_with(|| class_weight(grandparent)) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .and_then(|(id, _)| document.tree.get(id))
+        .and_then(ElementRef::wrap)
+}
+
+/// Scores an element based on whether its class name and id suggest that it
+/// contains the article.
+fn class_weight(element: ElementRef) -> f64 {
+    let element = element.value();
+    let names = [element.attr("class"), element.attr("id")]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_lowercase();
+
+    let mut weight = 0.0;
+
+    if POSITIVE_HINTS.iter().any(|hint| names.contains(hint)) {
+        weight += 25.0;
+    }
+
+    if NEGATIVE_HINTS.iter().any(|hint| names.contains(hint)) {
+        weight -= 25.0;
+    }
+
+    weight
+}
+
+fn serialize(node: NodeRef<Node>, page_url: &Url, out: &mut String) {
+    match node.value() {
+        Node::Text(text) => escape(text, out),
+        Node::Element(element) => serialize_element(node, element, page_url, out),
+        _ => {}
+    }
+}
+
+fn serialize_element(node: NodeRef<Node>, element: &Element, page_url: &Url, out: &mut String) {
+    let name = element.name();
+
+    if DROPPED_TAGS.contains(&name) {
+        return;
+    }
+
+    if !KEPT_TAGS.contains(&name) {
+        for child in node.children() {
+            serialize(child, page_url, out);
+        }
+        return;
+    }
+
+    out.push('<');
+    out.push_str(name);
+
+    let attrs: &[&str] = match name {
+        "a" => &["href", "title"],
+        "img" => &["src", "alt", "title"],
+        "td" | "th" => &["colspan", "rowspan"],
+        _ => &[],
+    };
+
+    for attr in attrs {
+        let Some(value) = element.attr(attr) else {
+            continue;
+        };
+
+        let value = match *attr {
+            "href" | "src" => match page_url.join(value.trim()) {
+                Ok(url) if matches!(url.scheme(), "http" | "https" | "mailto") => url.to_string(),
+                _ => continue,
+            },
+            _ => value.to_owned(),
+        };
+
+        let _ = write!(out, " {attr}=\"");
+        escape(&value, out);
+        out.push('"');
+    }
+
+    out.push('>');
+
+    if VOID_TAGS.contains(&name) {
+        return;
+    }
+
+    for child in node.children() {
+        serialize(child, page_url, out);
+    }
+
+    let _ = write!(out, "</{name}>");
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract_fixture(name: &str) -> Option<String> {
+        let path = format!(
+            "{}/tests/fixtures/readability/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let html =
+            std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+        extract_article(
+            &Html::parse_document(&html),
+            &Url::parse("https://example.com/posts/article").unwrap(),
+        )
+    }
+
+    /// Collapses the whitespace left over from the source's indentation.
+    fn collapse(html: &str) -> String {
+        html.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn extracts_article_elements() {
+        let article = collapse(&extract_fixture("article.html").unwrap());
+
+        assert_eq!(
+            article,
+            collapse(
+                r#"
+                <h1>An article</h1>
+                <p>The first paragraph of the article talks about something interesting, at length, with enough text that it counts as content.</p>
+                <p>It links to <a href="https://example.com/related">a related page</a>, <a>a script</a> and <a href="mailto:editor@example.com">the editor</a>.</p>
+                <figure><img src="https://example.com/posts/images/diagram.png" alt="A diagram"><figcaption>A diagram</figcaption></figure>
+                <p>The last paragraph wraps things up, and mentions that spans are unwrapped &amp; text is escaped.</p>
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn extracts_article_bodies() {
+        let paragraph = "<p>A paragraph of the article body, which is repeated a few times.</p>";
+        let html = format!(
+            r#"<div class="content"><p>Not this one, even though it is long enough to count, has a hint, and has commas.</p></div>
+            <div itemprop="articleBody">{}</div>"#,
+            paragraph.repeat(5)
+        );
+
+        let article = extract_article(
+            &Html::parse_document(&html),
+            &Url::parse("https://example.com/").unwrap(),
+        );
+
+        assert_eq!(article, Some(paragraph.repeat(5)));
+    }
+
+    #[test]
+    fn scores_paragraphs_without_article_elements() {
+        let article = collapse(&extract_fixture("scored.html").unwrap());
+
+        assert!(article.starts_with("<p>This page has no article element"));
+        assert!(article.ends_with("<p>Too short.</p>"));
+        assert!(!article.contains("comment"));
+        assert!(!article.contains("Welcome"));
+    }
+
+    #[test]
+    fn rejects_short_articles() {
+        assert_eq!(extract_fixture("short.html"), None);
+        assert_eq!(
+            extract_article(
+                &Html::parse_document("<p>Nothing to see here.</p>"),
+                &Url::parse("https://example.com/").unwrap(),
+            ),
+            None
+        );
+    }
+}
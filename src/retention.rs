@@ -0,0 +1,197 @@
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use itertools::Itertools;
+use migration::OnConflict;
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
+
+use crate::config::RetentionConfig;
+use crate::entities::prelude::*;
+use crate::entities::{
+    enclosures, feeds, playback_progress, post_revisions, post_tombstones, posts,
+};
+
+/// How often posts are checked against the retention policy.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Maximum number of posts deleted per statement, to stay well clear of
+/// SQLite's limit on bound parameters.
+const PRUNE_BATCH_SIZE: usize = 500;
+
+/// Upper bound for the maximum age set on a feed, which is long enough to
+/// keep posts forever in practice.
+pub const MAX_AGE_DAYS_LIMIT: i64 = 100 * 365;
+
+/// The retention policy that applies to a single feed.
+#[derive(Clone, Copy, Debug)]
+struct Policy {
+    max_age: Option<TimeDelta>,
+    keep_last: Option<usize>,
+}
+
+impl Policy {
+    /// Settings on the feed take precedence over the defaults. A value of 0
+    /// lifts the corresponding limit for the feed.
+    fn for_feed(feed: &feeds::Model, defaults: &RetentionConfig) -> Policy {
+        let max_age_days = feed
+            .retention_max_age_days
+            .or(defaults.max_age_days)
+            .filter(|&days| days > 0);
+
+        let keep_last = feed
+            .retention_keep_last
+            .or(defaults.keep_last)
+            .filter(|&count| count > 0);
+
+        Policy {
+            // Ages too large to represent can't be exceeded either.
+            max_age: max_age_days.and_then(TimeDelta::try_days),
+            keep_last: keep_last.map(|count| count as usize),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.max_age.is_none() && self.keep_last.is_none()
+    }
+}
+
+struct PrunablePost {
+    id: Uuid,
+    guid: String,
+    url: Option<String>,
+    published: DateTime<Utc>,
+}
+
+/// Prunes posts periodically, forever.
+pub async fn run_retention_worker(db: DatabaseConnection, config: RetentionConfig) {
+    loop {
+        match prune_posts(&db, &config).await {
+            Ok(0) => {}
+            Ok(pruned) => tracing::info!("pruned {pruned} posts"),
+            Err(e) => tracing::error!("failed to prune posts: {e:?}"),
+        }
+
+        tokio::time::sleep(PRUNE_INTERVAL).await;
+    }
+}
+
+/// Deletes posts that fall outside their feed's retention policy, leaving
+/// behind tombstones so that later syncs don't add them again.
+///
+/// Returns the number of posts deleted.
+pub async fn prune_posts(db: &DatabaseConnection, config: &RetentionConfig) -> eyre::Result<u64> {
+    let now = Utc::now();
+    let mut pruned = 0;
+
+    for feed in Feeds::find().all(db).await? {
+        let policy = Policy::for_feed(&feed, config);
+        if policy.is_unlimited() {
+            continue;
+        }
+
+        pruned += prune_feed(db, &feed, policy, now).await?;
+    }
+
+    Ok(pruned)
+}
+
+async fn prune_feed(
+    db: &DatabaseConnection,
+    feed: &feeds::Model,
+    policy: Policy,
+    now: DateTime<Utc>,
+) -> eyre::Result<u64> {
+    // Starred posts are exempt, and don't count towards `keep_last` either.
+    let posts: Vec<(Uuid, String, Option<String>, String)> = Posts::find()
+        .select_only()
+        .columns([
+            posts::Column::Id,
+            posts::Column::Guid,
+            posts::Column::Url,
+            posts::Column::PublishTime,
+        ])
+        .filter(posts::Column::FeedId.eq(feed.id))
+        .filter(posts::Column::Starred.eq(false))
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let posts = posts
+        .into_iter()
+        .map(|(id, guid, url, publish_time)| PrunablePost {
+            id,
+            guid,
+            url,
+            // Err on the side of keeping posts with unreadable times.
+            published: DateTime::parse_from_rfc3339(&publish_time)
+                .map(|time| time.to_utc())
+                .unwrap_or(now),
+        })
+        .sorted_by_key(|post| Reverse(post.published))
+        .enumerate()
+        .filter(|(index, post)| {
+            policy.keep_last.is_some_and(|count| *index >= count)
+                || policy.max_age.is_some_and(|age| now - post.published > age)
+        })
+        .map(|(_, post)| post)
+        .collect_vec();
+
+    if posts.is_empty() {
+        return Ok(0);
+    }
+
+    let txn = db.begin().await?;
+
+    for batch in posts.chunks(PRUNE_BATCH_SIZE) {
+        let ids = batch.iter().map(|post| post.id).collect_vec();
+
+        PostTombstones::insert_many(batch.iter().map(|post| post_tombstones::ActiveModel {
+            feed_id: ActiveValue::Set(feed.id),
+            guid: ActiveValue::Set(post.guid.clone()),
+            url: ActiveValue::Set(post.url.clone()),
+            pruned_at: ActiveValue::Set(now.timestamp()),
+            ..Default::default()
+        }))
+        .on_conflict(
+            OnConflict::columns([
+                post_tombstones::Column::FeedId,
+                post_tombstones::Column::Guid,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+
+        PostRevisions::delete_many()
+            .filter(post_revisions::Column::PostId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        Enclosures::delete_many()
+            .filter(enclosures::Column::PostId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        PlaybackProgress::delete_many()
+            .filter(playback_progress::Column::PostId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        Posts::delete_many()
+            .filter(posts::Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+
+    tracing::debug!(feed.url, count = posts.len(), "pruned posts");
+
+    Ok(posts.len() as u64)
+}
@@ -0,0 +1,470 @@
+use chrono::{DateTime, Datelike, TimeDelta, Timelike, Utc, Weekday};
+use rss::extension::syndication::UpdatePeriod;
+use sea_orm::{ActiveValue, JsonValue};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::entities::feeds;
+use crate::rdf;
+
+/// Lower bound for automatically computed sync intervals.
+pub const MIN_INTERVAL: TimeDelta = TimeDelta::minutes(5);
+/// Upper bound for automatically computed sync intervals.
+pub const MAX_INTERVAL: TimeDelta = TimeDelta::days(1);
+/// Upper bound for sync intervals set by the user.
+pub const MAX_USER_INTERVAL: TimeDelta = TimeDelta::days(365);
+/// Interval used when there is nothing to go on, e.g. for a feed that has
+/// never been fetched successfully.
+pub const DEFAULT_INTERVAL: TimeDelta = TimeDelta::hours(1);
+
+/// Upper bound for the delay between retries of a failing feed.
+pub const MAX_BACKOFF: TimeDelta = TimeDelta::days(1);
+/// Number of consecutive failures after which a feed is considered broken.
+pub const BROKEN_AFTER_FAILURES: i32 = 10;
+
+/// Number of recent posts considered when estimating posting frequency.
+pub const FREQUENCY_SAMPLE_SIZE: usize = 10;
+
+/// Scheduling hints published by the feed itself.
+#[derive(Debug, Default)]
+pub struct FeedHints {
+    /// The minimum time the feed asks to be cached for (RSS `<ttl>` or
+    /// `sy:updatePeriod`/`sy:updateFrequency`).
+    pub min_interval: Option<TimeDelta>,
+    /// Hours (0-23, UTC) during which the feed should not be fetched.
+    pub skip_hours: Vec<u32>,
+    /// Days during which the feed should not be fetched.
+    pub skip_days: Vec<Weekday>,
+}
+
+impl FeedHints {
+    pub fn from_rss(channel: &rss::Channel) -> FeedHints {
+        let ttl = channel
+            .ttl
+            .as_deref()
+            .and_then(|ttl| ttl.trim().parse().ok())
+            .and_then(TimeDelta::try_minutes);
+
+        let update_period = channel
+            .syndication_ext
+            .as_ref()
+            .map(|sy| update_period(&sy.period, sy.frequency));
+
+        FeedHints {
+            min_interval: capped_min_interval(ttl.max(update_period)),
+            skip_hours: channel
+                .skip_hours
+                .iter()
+                .filter_map(|hour| hour.trim().parse().ok())
+                .filter(|hour| *hour < 24)
+                .collect(),
+            skip_days: channel
+                .skip_days
+                .iter()
+                .filter_map(|day| day.trim().parse().ok())
+                .collect(),
+        }
+    }
+
+    pub fn from_rdf(feed: &rdf::Feed) -> FeedHints {
+        let period = feed
+            .update_period
+            .as_deref()
+            .and_then(|period| period.trim().parse().ok());
+        let frequency = feed
+            .update_frequency
+            .as_deref()
+            .and_then(|frequency| frequency.trim().parse().ok());
+
+        // Either element defaults as the syndication module specifies when
+        // only the other one is present.
+        let update_period = (period.is_some() || frequency.is_some()).then(|| {
+            update_period(
+                &period.unwrap_or(UpdatePeriod::Daily),
+                frequency.unwrap_or(1),
+            )
+        });
+
+        FeedHints {
+            min_interval: capped_min_interval(update_period),
+            ..FeedHints::default()
+        }
+    }
+
+    /// Returns the hints stored by the last full sync of a feed, for syncs
+    /// that don't get to see its content.
+    pub fn from_feed(feed: &feeds::Model) -> FeedHints {
+        FeedHints {
+            min_interval: feed.min_sync_interval.and_then(TimeDelta::try_seconds),
+            skip_hours: list_from_json(&feed.skip_hours),
+            skip_days: list_from_json(&feed.skip_days),
+        }
+    }
+
+    /// Stores the hints on a feed, to be read back by [`FeedHints::from_feed`].
+    pub fn store(&self, feed: &mut feeds::ActiveModel) {
+        feed.min_sync_interval =
+            ActiveValue::Set(self.min_interval.map(|interval| interval.num_seconds()));
+        feed.skip_hours = ActiveValue::Set(list_to_json(&self.skip_hours));
+        feed.skip_days = ActiveValue::Set(list_to_json(&self.skip_days));
+    }
+}
+
+/// Converts `sy:updatePeriod` and `sy:updateFrequency` to the time between
+/// updates.
+fn update_period(period: &UpdatePeriod, frequency: u32) -> TimeDelta {
+    let period = match period {
+        UpdatePeriod::Hourly => TimeDelta::hours(1),
+        UpdatePeriod::Daily => TimeDelta::days(1),
+        UpdatePeriod::Weekly => TimeDelta::weeks(1),
+        UpdatePeriod::Monthly => TimeDelta::days(30),
+        UpdatePeriod::Yearly => TimeDelta::days(365),
+    };
+    period / i32::try_from(frequency).unwrap_or(i32::MAX).max(1)
+}
+
+/// Feeds asking to be left alone for longer than [`MAX_INTERVAL`] are still
+/// synced every so often.
+fn capped_min_interval(min_interval: Option<TimeDelta>) -> Option<TimeDelta> {
+    min_interval.map(|min| min.min(MAX_INTERVAL))
+}
+
+fn list_from_json<T: DeserializeOwned>(value: &Option<JsonValue>) -> Vec<T> {
+    value
+        .clone()
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn list_to_json<T: Serialize>(values: &[T]) -> Option<JsonValue> {
+    if values.is_empty() {
+        None
+    } else {
+        serde_json::to_value(values).ok()
+    }
+}
+
+/// Converts a sync interval set by the user, in seconds.
+pub fn user_interval(seconds: i64) -> TimeDelta {
+    TimeDelta::try_seconds(seconds)
+        .unwrap_or(MAX_USER_INTERVAL)
+        .min(MAX_USER_INTERVAL)
+}
+
+/// Computes how long to wait before syncing a feed again.
+///
+/// An interval set by the user always wins. Otherwise the interval is derived
+/// from how often the feed publishes, and is never shorter than what the feed
+/// itself asks for.
+pub fn sync_interval(
+    user_interval: Option<TimeDelta>,
+    hints: &FeedHints,
+    now: DateTime<Utc>,
+    mut post_times: Vec<DateTime<Utc>>,
+) -> TimeDelta {
+    if let Some(interval) = user_interval {
+        return interval;
+    }
+
+    post_times.sort_unstable_by(|a, b| b.cmp(a));
+    post_times.truncate(FREQUENCY_SAMPLE_SIZE);
+
+    let observed = match (post_times.first(), post_times.last()) {
+        (Some(newest), Some(oldest)) if post_times.len() > 1 => {
+            let average_gap = (*newest - *oldest) / (post_times.len() - 1) as i32;
+            // A feed that has gone quiet for much longer than it usually
+            // posts is probably dormant, so don't keep polling it eagerly.
+            let since_newest = now - *newest;
+            // Poll a few times per expected post so new ones show up promptly.
+            (average_gap / 4).max(since_newest / 4)
+        }
+        _ => DEFAULT_INTERVAL,
+    };
+
+    let interval = observed.clamp(MIN_INTERVAL, MAX_INTERVAL);
+
+    match hints.min_interval {
+        Some(min) => interval.max(min),
+        None => interval,
+    }
+}
+
+/// Computes how long to wait before retrying a feed that has failed
+/// `failures` times in a row, doubling the usual interval after each failure.
+pub fn failure_backoff(interval: TimeDelta, failures: i32) -> TimeDelta {
+    let exponent = failures.saturating_sub(1).clamp(0, 16) as u32;
+    let backoff = interval
+        .checked_mul(2i32.pow(exponent))
+        .unwrap_or(MAX_BACKOFF);
+    backoff.min(MAX_BACKOFF.max(interval))
+}
+
+/// Returns the earliest time at or after `now + interval` that isn't excluded
+/// by the feed's `skipHours`/`skipDays`.
+pub fn next_sync_at(now: DateTime<Utc>, interval: TimeDelta, hints: &FeedHints) -> DateTime<Utc> {
+    let later = |time: DateTime<Utc>, delta| {
+        time.checked_add_signed(delta)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    };
+
+    let mut next = later(now, interval);
+
+    // A week of hours is enough to get past any combination of skipped
+    // hours and days, unless the feed asks to be skipped entirely.
+    for _ in 0..(7 * 24) {
+        let skipped =
+            hints.skip_hours.contains(&next.hour()) || hints.skip_days.contains(&next.weekday());
+
+        if !skipped {
+            return next;
+        }
+
+        next = later(
+            next.with_minute(0)
+                .and_then(|t| t.with_second(0))
+                .and_then(|t| t.with_nanosecond(0))
+                .unwrap_or(next),
+            TimeDelta::hours(1),
+        );
+    }
+
+    later(now, interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use rss::extension::syndication::SyndicationExtension;
+
+    use super::*;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+
+    /// Post times `gap` apart, the newest of which is `since_newest` before
+    /// `now`.
+    fn posts(now: DateTime<Utc>, since_newest: TimeDelta, gap: TimeDelta) -> Vec<DateTime<Utc>> {
+        (0..5).map(|i| now - since_newest - gap * i).collect()
+    }
+
+    fn min_interval(interval: TimeDelta) -> FeedHints {
+        FeedHints {
+            min_interval: Some(interval),
+            ..FeedHints::default()
+        }
+    }
+
+    #[test]
+    fn prefers_the_user_interval() {
+        let now = time("2024-01-01T12:00:00Z");
+        let posts = posts(now, TimeDelta::zero(), TimeDelta::minutes(1));
+
+        assert_eq!(
+            sync_interval(
+                Some(TimeDelta::days(3)),
+                &min_interval(TimeDelta::hours(1)),
+                now,
+                posts.clone()
+            ),
+            TimeDelta::days(3)
+        );
+        assert_eq!(
+            sync_interval(
+                Some(TimeDelta::minutes(1)),
+                &min_interval(TimeDelta::hours(1)),
+                now,
+                posts
+            ),
+            TimeDelta::minutes(1)
+        );
+    }
+
+    #[test]
+    fn follows_the_posting_frequency() {
+        let now = time("2024-01-01T12:00:00Z");
+        let hints = FeedHints::default();
+
+        assert_eq!(sync_interval(None, &hints, now, vec![]), DEFAULT_INTERVAL);
+        assert_eq!(
+            sync_interval(None, &hints, now, vec![now]),
+            DEFAULT_INTERVAL
+        );
+        assert_eq!(
+            sync_interval(
+                None,
+                &hints,
+                now,
+                posts(now, TimeDelta::zero(), TimeDelta::hours(4))
+            ),
+            TimeDelta::hours(1)
+        );
+        assert_eq!(
+            sync_interval(
+                None,
+                &hints,
+                now,
+                posts(now, TimeDelta::zero(), TimeDelta::minutes(1))
+            ),
+            MIN_INTERVAL
+        );
+        assert_eq!(
+            sync_interval(
+                None,
+                &hints,
+                now,
+                posts(now, TimeDelta::zero(), TimeDelta::weeks(1))
+            ),
+            MAX_INTERVAL
+        );
+    }
+
+    #[test]
+    fn treats_feed_hints_as_a_lower_bound() {
+        let now = time("2024-01-01T12:00:00Z");
+        let posts = posts(now, TimeDelta::zero(), TimeDelta::hours(4));
+
+        // `<ttl>120</ttl>`
+        assert_eq!(
+            sync_interval(None, &min_interval(TimeDelta::hours(2)), now, posts.clone()),
+            TimeDelta::hours(2)
+        );
+        // `sy:updatePeriod` of `hourly` with a frequency of 4
+        assert_eq!(
+            sync_interval(None, &min_interval(TimeDelta::minutes(15)), now, posts),
+            TimeDelta::hours(1)
+        );
+    }
+
+    #[test]
+    fn backs_off_on_dormant_feeds() {
+        let now = time("2024-01-01T12:00:00Z");
+        let hints = FeedHints::default();
+
+        assert_eq!(
+            sync_interval(
+                None,
+                &hints,
+                now,
+                posts(now, TimeDelta::hours(8), TimeDelta::hours(4))
+            ),
+            TimeDelta::hours(2)
+        );
+        assert_eq!(
+            sync_interval(
+                None,
+                &hints,
+                now,
+                posts(now, TimeDelta::days(30), TimeDelta::hours(4))
+            ),
+            MAX_INTERVAL
+        );
+    }
+
+    #[test]
+    fn doubles_the_interval_after_each_failure() {
+        let hour = TimeDelta::hours(1);
+
+        assert_eq!(failure_backoff(hour, 0), hour);
+        assert_eq!(failure_backoff(hour, 1), hour);
+        assert_eq!(failure_backoff(hour, 2), TimeDelta::hours(2));
+        assert_eq!(failure_backoff(hour, 4), TimeDelta::hours(8));
+        assert_eq!(failure_backoff(hour, 6), MAX_BACKOFF);
+        assert_eq!(failure_backoff(hour, i32::MAX), MAX_BACKOFF);
+        assert_eq!(failure_backoff(MAX_USER_INTERVAL, 3), MAX_USER_INTERVAL);
+    }
+
+    #[test]
+    fn skips_hours_and_days() {
+        let hour = TimeDelta::hours(1);
+        let hints = FeedHints {
+            min_interval: None,
+            skip_hours: vec![3, 4],
+            skip_days: vec![Weekday::Sun],
+        };
+
+        assert_eq!(
+            next_sync_at(time("2024-01-01T00:30:00Z"), hour, &hints),
+            time("2024-01-01T01:30:00Z")
+        );
+        // 03:30 and 04:00 are skipped.
+        assert_eq!(
+            next_sync_at(time("2024-01-01T02:30:00Z"), hour, &hints),
+            time("2024-01-01T05:00:00Z")
+        );
+        // Saturday 23:30 rolls over into Sunday, and then Monday's skipped
+        // hours.
+        assert_eq!(
+            next_sync_at(time("2024-01-06T23:30:00Z"), hour, &hints),
+            time("2024-01-08T00:00:00Z")
+        );
+        assert_eq!(
+            next_sync_at(time("2024-01-07T02:30:00Z"), hour, &hints),
+            time("2024-01-08T00:00:00Z")
+        );
+
+        // Feeds that ask to never be synced still are.
+        let everything = FeedHints {
+            min_interval: None,
+            skip_hours: (0..24).collect(),
+            skip_days: vec![],
+        };
+        assert_eq!(
+            next_sync_at(time("2024-01-01T00:30:00Z"), hour, &everything),
+            time("2024-01-01T01:30:00Z")
+        );
+    }
+
+    #[test]
+    fn reads_rss_hints() {
+        let mut channel = rss::Channel {
+            ttl: Some("90".to_owned()),
+            syndication_ext: Some(SyndicationExtension {
+                period: UpdatePeriod::Hourly,
+                frequency: 1,
+                ..SyndicationExtension::default()
+            }),
+            skip_hours: vec!["3".to_owned(), " 25 ".to_owned(), "noon".to_owned()],
+            skip_days: vec!["Sunday".to_owned(), "Someday".to_owned()],
+            ..rss::Channel::default()
+        };
+
+        let hints = FeedHints::from_rss(&channel);
+        assert_eq!(hints.min_interval, Some(TimeDelta::minutes(90)));
+        assert_eq!(hints.skip_hours, [3]);
+        assert_eq!(hints.skip_days, [Weekday::Sun]);
+
+        channel.ttl = Some("100000".to_owned());
+        assert_eq!(
+            FeedHints::from_rss(&channel).min_interval,
+            Some(MAX_INTERVAL)
+        );
+
+        channel.ttl = None;
+        channel.syndication_ext = None;
+        assert_eq!(FeedHints::from_rss(&channel).min_interval, None);
+    }
+
+    #[test]
+    fn reads_rdf_hints() {
+        let hints = |period: Option<&str>, frequency: Option<&str>| {
+            let feed = rdf::Feed {
+                title: String::new(),
+                link: None,
+                image: None,
+                update_period: period.map(ToOwned::to_owned),
+                update_frequency: frequency.map(ToOwned::to_owned),
+                items: vec![],
+            };
+            FeedHints::from_rdf(&feed).min_interval
+        };
+
+        assert_eq!(
+            hints(Some("hourly"), Some("4")),
+            Some(TimeDelta::minutes(15))
+        );
+        assert_eq!(hints(None, Some("4")), Some(TimeDelta::hours(6)));
+        assert_eq!(hints(Some("hourly"), None), Some(TimeDelta::hours(1)));
+        assert_eq!(hints(Some("weekly"), Some("0")), Some(MAX_INTERVAL));
+        assert_eq!(hints(None, None), None);
+    }
+}
@@ -0,0 +1,33 @@
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+/// Images describing a website, scraped from its homepage.
+#[derive(Debug, Default)]
+pub struct SiteImages {
+    pub icon: Option<String>,
+    pub image: Option<String>,
+}
+
+pub fn find_site_images(html: &str, base: &Url) -> SiteImages {
+    let html = Html::parse_document(html);
+
+    let find = |selector: &str, attr: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        html.select(&selector)
+            .filter_map(|el| el.attr(attr))
+            .find_map(|url| base.join(url.trim()).ok())
+            .map(String::from)
+    };
+
+    SiteImages {
+        // Touch icons tend to be higher resolution than regular favicons.
+        icon: find("link[rel~=\"apple-touch-icon\"][href]", "href")
+            .or_else(|| find("link[rel~=\"icon\"][href]", "href")),
+        image: find("meta[property=\"og:image\"][content]", "content"),
+    }
+}
+
+/// The conventional favicon location for a site.
+pub fn default_favicon_url(site: &Url) -> Option<Url> {
+    site.join("/favicon.ico").ok()
+}
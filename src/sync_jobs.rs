@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// How long finished jobs are kept around for their status to be queried.
+const FINISHED_JOB_RETENTION: TimeDelta = TimeDelta::hours(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncJobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyncJob {
+    pub id: Uuid,
+    pub status: SyncJobStatus,
+    /// Number of posts added by the job so far.
+    pub new_posts: u64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Tracks the progress of manually requested syncs, so that clients can poll
+/// for their results.
+///
+/// Jobs are only kept in memory, and are forgotten some time after finishing.
+pub struct SyncJobs {
+    jobs: Mutex<HashMap<Uuid, SyncJob>>,
+}
+
+impl SyncJobs {
+    pub fn new() -> SyncJobs {
+        SyncJobs {
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers a new queued job.
+    pub fn create(&self) -> SyncJob {
+        let now = Utc::now();
+        let job = SyncJob {
+            id: Uuid::new_v4(),
+            status: SyncJobStatus::Queued,
+            new_posts: 0,
+            error: None,
+            created_at: now,
+            finished_at: None,
+        };
+
+        let mut jobs = self.jobs.lock();
+
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished_at| now - finished_at < FINISHED_JOB_RETENTION)
+        });
+
+        jobs.insert(job.id, job.clone());
+
+        job
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<SyncJob> {
+        self.jobs.lock().get(&id).cloned()
+    }
+
+    pub fn start(&self, id: Uuid) {
+        self.update(id, |job| job.status = SyncJobStatus::Running);
+    }
+
+    pub fn add_new_posts(&self, id: Uuid, count: u64) {
+        self.update(id, |job| job.new_posts += count);
+    }
+
+    /// Marks a job as finished, failing it if an error is given.
+    pub fn finish(&self, id: Uuid, error: Option<String>) {
+        self.update(id, |job| {
+            job.status = match error {
+                Some(_) => SyncJobStatus::Failed,
+                None => SyncJobStatus::Done,
+            };
+            job.error = error;
+            job.finished_at = Some(Utc::now());
+        });
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut SyncJob)) {
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            f(job);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Finds thumbnails for posts, preferring images referenced by the feed itself
+//! over ones that need the article page to be fetched.
+//!
+//! All returned urls are resolved against the given base url.
+
+use std::collections::BTreeMap;
+
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use crate::{json_feed, rdf};
+
+/// An extension element from either an RSS or an Atom feed, which both store
+/// Media RSS elements the same way.
+trait MediaElement: Sized {
+    fn attr(&self, name: &str) -> Option<&str>;
+    fn children(&self) -> &BTreeMap<String, Vec<Self>>;
+}
+
+impl MediaElement for rss::extension::Extension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(String::as_str)
+    }
+
+    fn children(&self) -> &BTreeMap<String, Vec<Self>> {
+        &self.children
+    }
+}
+
+impl MediaElement for atom_syndication::extension::Extension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(String::as_str)
+    }
+
+    fn children(&self) -> &BTreeMap<String, Vec<Self>> {
+        &self.children
+    }
+}
+
+pub fn from_rss_item(item: &rss::Item, base: &Url) -> Option<String> {
+    let image = item
+        .extensions
+        .get("media")
+        .and_then(media_image)
+        .or_else(|| {
+            item.enclosure
+                .as_ref()
+                .filter(|enclosure| is_image_type(&enclosure.mime_type))
+                .map(|enclosure| enclosure.url.as_str())
+        });
+
+    if let Some(image) = image {
+        return resolve(image, base);
+    }
+
+    item.content
+        .as_deref()
+        .or(item.description.as_deref())
+        .and_then(|html| first_image(html, base))
+}
+
+pub fn from_atom_entry(entry: &atom_syndication::Entry, base: &Url) -> Option<String> {
+    let image = entry
+        .extensions
+        .get("media")
+        .and_then(media_image)
+        .or_else(|| {
+            entry
+                .links
+                .iter()
+                .find(|link| {
+                    link.rel == "enclosure" && link.mime_type.as_deref().is_some_and(is_image_type)
+                })
+                .map(|link| link.href.as_str())
+        });
+
+    if let Some(image) = image {
+        return resolve(image, base);
+    }
+
+    entry
+        .content
+        .as_ref()
+        .and_then(|content| content.value.as_deref())
+        .or(entry.summary.as_ref().map(|summary| summary.value.as_str()))
+        .and_then(|html| first_image(html, base))
+}
+
+pub fn from_json_item(item: &json_feed::Item, base: &Url) -> Option<String> {
+    let image = item
+        .image
+        .as_deref()
+        .or_else(|| item.image_attachment().map(|a| a.url.as_str()));
+
+    if let Some(image) = image {
+        return resolve(image, base);
+    }
+
+    item.content_html
+        .as_deref()
+        .and_then(|html| first_image(html, base))
+}
+
+pub fn from_rdf_item(item: &rdf::Item, base: &Url) -> Option<String> {
+    item.content
+        .as_deref()
+        .or(item.description.as_deref())
+        .and_then(|html| first_image(html, base))
+}
+
+/// Finds the image a web page advertises for link previews.
+pub fn from_page(document: &Html, base: &Url) -> Option<String> {
+    let find = |selector: &str, attr: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        document
+            .select(&selector)
+            .filter_map(|el| el.attr(attr))
+            .find_map(|url| resolve(url, base))
+    };
+
+    find("meta[property=\"og:image\"][content]", "content")
+        .or_else(|| find("meta[property=\"og:image:url\"][content]", "content"))
+        .or_else(|| {
+            find(
+                "meta[name=\"twitter:image\"][content], meta[property=\"twitter:image\"][content]",
+                "content",
+            )
+        })
+        .or_else(|| find("meta[name=\"twitter:image:src\"][content]", "content"))
+        .or_else(|| find("link[rel~=\"image_src\"][href]", "href"))
+}
+
+/// Finds an image in the `media` namespace elements of an item, which may be
+/// nested inside a `media:group`.
+fn media_image<E: MediaElement>(media: &BTreeMap<String, Vec<E>>) -> Option<&str> {
+    let elements = |name: &str| media.get(name).into_iter().flatten();
+
+    elements("thumbnail")
+        .find_map(|thumbnail| thumbnail.attr("url"))
+        .or_else(|| {
+            elements("content")
+                .filter(|content| {
+                    content.attr("medium") == Some("image")
+                        || content.attr("type").is_some_and(is_image_type)
+                })
+                .find_map(|content| content.attr("url"))
+        })
+        .or_else(|| {
+            // Videos and other non-image content can have their own thumbnails.
+            elements("content").find_map(|content| media_image(content.children()))
+        })
+        .or_else(|| elements("group").find_map(|group| media_image(group.children())))
+}
+
+/// Finds the first image in an HTML fragment.
+fn first_image(html: &str, base: &Url) -> Option<String> {
+    let html = Html::parse_fragment(html);
+    let selector = Selector::parse("img[src]").unwrap();
+
+    html.select(&selector)
+        .filter_map(|img| img.attr("src"))
+        .find_map(|src| resolve(src, base))
+}
+
+fn is_image_type(mime_type: &str) -> bool {
+    mime_type.trim().to_ascii_lowercase().starts_with("image/")
+}
+
+fn resolve(url: &str, base: &Url) -> Option<String> {
+    let url = base.join(url.trim()).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.into())
+}
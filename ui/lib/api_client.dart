@@ -1,3 +1,5 @@
+import 'dart:convert';
+
 import 'package:dio/dio.dart';
 
 import 'auth_service.dart';
@@ -24,6 +26,17 @@ class ApiClient {
     }
   }
 
+  /// Returns the message the server gave for a failed request, if any.
+  static String? errorMessage(Object error) {
+    if (error is DioException) {
+      final data = error.response?.data;
+      if (data is Map<String, dynamic>) {
+        return data['message'];
+      }
+    }
+    return null;
+  }
+
   Future<Map<String, dynamic>> getConfig() async {
     final res = await _dio.get('$_baseUrl/config');
     return res.data;
@@ -49,14 +62,91 @@ class ApiClient {
     return Post.fromJson(res.data);
   }
 
-  Future<void> addFeed(String url) async {
-    await _dio.post('$_baseUrl/feeds', data: {'url': url});
+  /// Starred posts are exempt from retention pruning.
+  Future<Post> setPostStarred(String id, bool starred) async {
+    final res = await _dio.patch(
+      '$_baseUrl/posts/$id',
+      data: {'starred': starred},
+    );
+    return Post.fromJson(res.data);
+  }
+
+  /// Adds a feed. If [url] points at a page that links to several feeds,
+  /// nothing is added and the feeds are returned to pick from instead.
+  Future<List<FeedCandidate>> addFeed(String url) async {
+    final res = await _dio.post(
+      '$_baseUrl/feeds',
+      data: {'url': url},
+      options: Options(
+        validateStatus: (status) =>
+            status != null && status >= 200 && status <= 300,
+      ),
+    );
+    if (res.statusCode == 300) {
+      return (res.data['feeds'] as List<dynamic>)
+          .map((e) => FeedCandidate.fromJson(e))
+          .toList();
+    }
+    return [];
   }
 
   Future<void> deleteFeed(String id) async {
     await _dio.delete('$_baseUrl/feeds/$id');
   }
 
+  Future<SyncJob> syncFeed(String id) async {
+    final res = await _dio.post('$_baseUrl/feeds/$id/sync');
+    return SyncJob.fromJson(res.data);
+  }
+
+  Future<SyncJob> syncAll() async {
+    final res = await _dio.post('$_baseUrl/sync');
+    return SyncJob.fromJson(res.data);
+  }
+
+  Future<SyncJob> getSyncJob(String id) async {
+    final res = await _dio.get('$_baseUrl/sync/jobs/$id');
+    return SyncJob.fromJson(res.data);
+  }
+
+  /// Polls a sync job until it has finished.
+  Future<SyncJob> waitForSyncJob(SyncJob job) async {
+    while (!job.isFinished) {
+      await Future.delayed(const Duration(seconds: 1));
+      job = await getSyncJob(job.id);
+    }
+    return job;
+  }
+
+  /// Streams live events from the server until the connection is closed.
+  Stream<ServerEvent> events() async* {
+    final res = await _dio.get<ResponseBody>(
+      '$_baseUrl/events',
+      options: Options(responseType: ResponseType.stream),
+    );
+
+    final lines = const LineSplitter().bind(
+      utf8.decoder.bind(res.data!.stream),
+    );
+
+    String? type;
+    final data = StringBuffer();
+
+    await for (final line in lines) {
+      if (line.isEmpty) {
+        if (type != null && data.isNotEmpty) {
+          yield ServerEvent(type: type, data: jsonDecode(data.toString()));
+        }
+        type = null;
+        data.clear();
+      } else if (line.startsWith('event:')) {
+        type = line.substring(6).trim();
+      } else if (line.startsWith('data:')) {
+        data.write(line.substring(5).trim());
+      }
+    }
+  }
+
   Future<void> registerPushSubscription(
     String endpoint,
     String? auth,
@@ -2,25 +2,54 @@ class Feed {
   final String id;
   final String title;
   final String url;
+  final String? icon;
+  final String? thumbnail;
   final DateTime? lastSyncedAt;
+  final String? lastError;
+  final bool broken;
+  final bool isPodcast;
+  final bool active;
 
   Feed({
     required this.id,
     required this.title,
     required this.url,
+    required this.icon,
+    required this.thumbnail,
     required this.lastSyncedAt,
+    required this.lastError,
+    required this.broken,
+    required this.isPodcast,
+    required this.active,
   });
 
   factory Feed.fromJson(Map<String, dynamic> json) => Feed(
     id: json['id'],
     title: json['title'],
     url: json['url'],
+    icon: json['icon'],
+    thumbnail: json['thumbnail'],
     lastSyncedAt: json['last_synced_at'] != null
         ? DateTime.fromMillisecondsSinceEpoch(json['last_synced_at'] * 1000)
         : null,
+    lastError: json['last_error'],
+    broken: json['broken'] ?? false,
+    isPodcast: json['is_podcast'] ?? false,
+    active: json['active'] ?? true,
   );
 }
 
+/// A feed found on a web page that links to several of them.
+class FeedCandidate {
+  final String url;
+  final String? title;
+
+  FeedCandidate({required this.url, required this.title});
+
+  factory FeedCandidate.fromJson(Map<String, dynamic> json) =>
+      FeedCandidate(url: json['url'], title: json['title']);
+}
+
 class Post {
   final String id;
   final String feedId;
@@ -28,7 +57,9 @@ class Post {
   final DateTime postTime;
   final String? thumbnail;
   final String? description;
-  final String url;
+  final String? url;
+  final bool starred;
+  final List<Enclosure> enclosures;
 
   Post({
     required this.id,
@@ -38,6 +69,8 @@ class Post {
     required this.thumbnail,
     required this.description,
     required this.url,
+    required this.starred,
+    required this.enclosures,
   });
 
   factory Post.fromJson(Map<String, dynamic> json) => Post(
@@ -48,5 +81,63 @@ class Post {
     thumbnail: json['thumbnail'],
     description: json['description'],
     url: json['url'],
+    starred: json['starred'] ?? false,
+    enclosures: [
+      for (final enclosure in json['enclosures'] ?? [])
+        Enclosure.fromJson(enclosure),
+    ],
+  );
+}
+
+class Enclosure {
+  final String url;
+  final String? mimeType;
+  final int? length;
+  final Duration? duration;
+
+  Enclosure({
+    required this.url,
+    required this.mimeType,
+    required this.length,
+    required this.duration,
+  });
+
+  factory Enclosure.fromJson(Map<String, dynamic> json) => Enclosure(
+    url: json['url'],
+    mimeType: json['mime_type'],
+    length: json['length'],
+    duration: json['duration'] != null
+        ? Duration(seconds: json['duration'])
+        : null,
+  );
+}
+
+class SyncJob {
+  final String id;
+  final String status;
+  final int newPosts;
+  final String? error;
+
+  SyncJob({
+    required this.id,
+    required this.status,
+    required this.newPosts,
+    required this.error,
+  });
+
+  bool get isFinished => status == 'done' || status == 'failed';
+
+  factory SyncJob.fromJson(Map<String, dynamic> json) => SyncJob(
+    id: json['id'],
+    status: json['status'],
+    newPosts: json['new_posts'],
+    error: json['error'],
   );
 }
+
+class ServerEvent {
+  final String type;
+  final Map<String, dynamic> data;
+
+  ServerEvent({required this.type, required this.data});
+}
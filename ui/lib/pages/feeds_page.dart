@@ -47,6 +47,35 @@ class _FeedsPageState extends State<FeedsPage> {
     }
   }
 
+  Future<void> _addFeed(String url) async {
+    try {
+      final candidates = await _apiClient.addFeed(url);
+      if (candidates.isNotEmpty) {
+        if (!mounted) return;
+        final candidate = await showDialog<FeedCandidate>(
+          context: context,
+          builder: (context) => _FeedCandidatesDialog(candidates: candidates),
+        );
+        if (candidate == null) return;
+        await _apiClient.addFeed(candidate.url);
+      }
+      await _loadData();
+    } catch (e) {
+      if (mounted) {
+        final message = ApiClient.errorMessage(e);
+        ScaffoldMessenger.of(context).showSnackBar(
+          SnackBar(
+            content: Text(
+              message != null
+                  ? 'Failed to add feed: $message'
+                  : 'Failed to add feed',
+            ),
+          ),
+        );
+      }
+    }
+  }
+
   Future<void> _loadData() async {
     try {
       final feeds = await _apiClient.getFeeds();
@@ -92,24 +121,11 @@ class _FeedsPageState extends State<FeedsPage> {
                         child: const Text('Cancel'),
                       ),
                       TextButton(
-                        onPressed: () async {
+                        onPressed: () {
                           final url = _urlController.text;
-                          try {
-                            await _apiClient.addFeed(url);
-                            await _loadData();
-                          } catch (e) {
-                            if (context.mounted) {
-                              ScaffoldMessenger.of(context).showSnackBar(
-                                const SnackBar(
-                                  content: Text('Failed to add feed'),
-                                ),
-                              );
-                            }
-                          }
-                          if (context.mounted) {
-                            Navigator.pop(context);
-                            _urlController.clear();
-                          }
+                          Navigator.pop(context);
+                          _urlController.clear();
+                          _addFeed(url);
                         },
                         child: const Text('Ok'),
                       ),
@@ -139,7 +155,32 @@ class _FeedsPageState extends State<FeedsPage> {
                     )
                   : null;
               return ListTile(
-                leading: const Icon(Icons.rss_feed),
+                leading: !feed.active
+                    ? Tooltip(
+                        message: 'Feed no longer exists',
+                        child: Icon(
+                          Icons.block,
+                          color: Theme.of(context).colorScheme.error,
+                        ),
+                      )
+                    : feed.broken
+                    ? Tooltip(
+                        message: feed.lastError ?? 'Feed is failing to sync',
+                        child: Icon(
+                          Icons.error_outline,
+                          color: Theme.of(context).colorScheme.error,
+                        ),
+                      )
+                    : switch (feed.icon) {
+                        String icon => Image.network(
+                          icon,
+                          width: 24,
+                          height: 24,
+                          errorBuilder: (context, error, stackTrace) =>
+                              const Icon(Icons.rss_feed),
+                        ),
+                        null => const Icon(Icons.rss_feed),
+                      },
                 title: Text(feed.title),
                 subtitle: Column(
                   crossAxisAlignment: CrossAxisAlignment.start,
@@ -191,3 +232,26 @@ class _DeleteFeedDialog extends StatelessWidget {
     );
   }
 }
+
+class _FeedCandidatesDialog extends StatelessWidget {
+  const _FeedCandidatesDialog({required this.candidates});
+
+  final List<FeedCandidate> candidates;
+
+  @override
+  Widget build(BuildContext context) {
+    return SimpleDialog(
+      title: const Text('Choose a Feed'),
+      children: [
+        for (final candidate in candidates)
+          ListTile(
+            title: Text(candidate.title ?? candidate.url),
+            subtitle: candidate.title != null
+                ? Text(candidate.url, overflow: TextOverflow.ellipsis)
+                : null,
+            onTap: () => Navigator.pop(context, candidate),
+          ),
+      ],
+    );
+  }
+}
@@ -1,3 +1,5 @@
+import 'dart:async';
+
 import 'package:auto_route/auto_route.dart';
 import 'package:cached_network_image/cached_network_image.dart';
 import 'package:flutter/material.dart';
@@ -25,15 +27,31 @@ class _PostsPageState extends State<PostsPage> {
   Map<String, Feed>? _feeds;
   List<Post>? _posts;
   Object? _error;
+  StreamSubscription<ServerEvent>? _events;
 
   @override
   void initState() {
     super.initState();
     _apiClient = GetIt.instance<ApiClient>();
     _loadData();
+    _events = _apiClient.events().listen(
+      (event) {
+        // Reload once per sync rather than for every new post.
+        if (event.type == 'feed_synced' && event.data['new_posts'] > 0) {
+          _loadData();
+        }
+      },
+      onError: (e) {},
+    );
     Permission.notification.request();
   }
 
+  @override
+  void dispose() {
+    _events?.cancel();
+    super.dispose();
+  }
+
   Future<void> _loadData() async {
     try {
       final [feeds, posts] = await Future.wait([
@@ -55,19 +73,54 @@ class _PostsPageState extends State<PostsPage> {
     }
   }
 
+  Future<void> _refresh() async {
+    try {
+      final job = await _apiClient.waitForSyncJob(await _apiClient.syncAll());
+      if (job.error != null && mounted) {
+        ScaffoldMessenger.of(context).showSnackBar(
+          const SnackBar(content: Text('Failed to sync feeds')),
+        );
+      }
+    } catch (e) {
+      if (mounted) {
+        ScaffoldMessenger.of(context).showSnackBar(
+          const SnackBar(content: Text('Failed to sync feeds')),
+        );
+      }
+    }
+    await _loadData();
+  }
+
+  Future<void> _toggleStarred(Post post) async {
+    try {
+      await _apiClient.setPostStarred(post.id, !post.starred);
+      await _loadData();
+    } catch (e) {
+      if (mounted) {
+        ScaffoldMessenger.of(context).showSnackBar(
+          const SnackBar(content: Text('Failed to update post')),
+        );
+      }
+    }
+  }
+
   @override
   Widget build(BuildContext context) {
     return Scaffold(
       appBar: AppBar(title: const Text('Posts')),
       body: switch ((_feeds, _posts, _error)) {
         (final feeds?, final posts?, _) => RefreshIndicator(
-          onRefresh: _loadData,
+          onRefresh: _refresh,
           child: ListView.separated(
             padding: const EdgeInsets.all(4),
             itemCount: posts.length,
             itemBuilder: (context, index) {
               final post = posts[index];
-              return _PostTile(feed: feeds[post.feedId]!, post: post);
+              return _PostTile(
+                feed: feeds[post.feedId]!,
+                post: post,
+                onLongPress: () => _toggleStarred(post),
+              );
             },
             separatorBuilder: (context, index) => const Gap(4),
           ),
@@ -84,8 +137,13 @@ class _PostsPageState extends State<PostsPage> {
 final class _PostTile extends StatelessWidget {
   final Feed feed;
   final Post post;
+  final VoidCallback onLongPress;
 
-  const _PostTile({required this.feed, required this.post});
+  const _PostTile({
+    required this.feed,
+    required this.post,
+    required this.onLongPress,
+  });
 
   @override
   Widget build(BuildContext context) {
@@ -135,6 +193,14 @@ final class _PostTile extends StatelessWidget {
                           Expanded(
                             child: Text(feed.title, style: overlineStyle),
                           ),
+                          if (post.starred) ...[
+                            Icon(
+                              Icons.star,
+                              size: 14,
+                              color: theme.colorScheme.primary,
+                            ),
+                            const Gap(4),
+                          ],
                           Text(
                             _dateFormat.format(post.postTime),
                             style: overlineStyle,
@@ -156,9 +222,13 @@ final class _PostTile extends StatelessWidget {
             ],
           ),
         ),
-        onTap: () async {
-          await launchUrlString(post.url);
+        onTap: switch (post.url) {
+          String url => () async {
+            await launchUrlString(url);
+          },
+          null => null,
         },
+        onLongPress: onLongPress,
       ),
     );
   }